@@ -0,0 +1,49 @@
+//! Throughput of method dispatch depending on worker count
+//!
+//! Run with `cargo bench --bench dispatch`. Workers send requests to in-memory mock
+//! of VK API every millisecond, so throughput should grow with worker count
+//! until dispatch itself becomes the bottleneck.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use common::mock::MockVk;
+use futures::future::join_all;
+use vk_executive::{Client, Method};
+use vk_method::{PairsArray, Params};
+
+use std::time::Instant;
+
+const METHODS: u64 = 100_000;
+
+fn run(workers: usize) -> f64 {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    runtime.block_on(async {
+        let mock = MockVk::users();
+        let client = Client::from_configs(mock.configs(workers).into_iter());
+
+        let methods = (0..METHODS).map(|user_id| {
+            Method::new(
+                "users.get",
+                Params::try_from(PairsArray([("user_id", user_id)])).unwrap(),
+            )
+        });
+
+        let start = Instant::now();
+        let results = join_all(methods.map(|method| client.method(method))).await;
+        let elapsed = start.elapsed();
+
+        assert!(results.iter().all(Result::is_ok));
+
+        METHODS as f64 / elapsed.as_secs_f64()
+    })
+}
+
+fn main() {
+    println!("{:>8} {:>14}", "workers", "methods/sec");
+
+    for workers in [1, 2, 4, 8, 16, 32, 64, 128] {
+        println!("{workers:>8} {:>14.0}", run(workers));
+    }
+}
@@ -0,0 +1,161 @@
+//! Synchronous wrapper of [`Client`](crate::Client)
+//!
+//! [`Client`] owns a multi-threaded tokio runtime, workers run there
+//! and every call blocks the current thread until the response is received.
+//! It must not be used from within an async context, calls panic there.
+
+use crate::client::{self, stats::Stats, HttpsClient, HyperClient, Options};
+use crate::{Config, Error, Result};
+use vk_method::Method;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use std::io;
+use std::iter::ExactSizeIterator;
+use std::sync::Arc;
+
+use http::request::Request;
+use hyper::body::Body;
+use tokio::runtime::{self, Runtime};
+use tower::Service;
+
+/// Blocking [`Client`](crate::Client)
+///
+/// # Example:
+/// ```rust,no_run
+/// use vk_executive::{blocking, Config, Method};
+/// use vk_method::Params;
+///
+/// let configs = Config::from_tokens(["123456789"].into_iter()).unwrap();
+/// let client = blocking::Client::from_configs(configs.into_iter()).unwrap();
+///
+/// let mut params = Params::new();
+/// params.insert("user_id", 1);
+///
+/// let response = client.method(Method::new("users.get", params)).unwrap();
+/// ```
+pub struct Client<C: HttpsClient = HyperClient>
+where
+    <C as Service<Request<Body>>>::Future: Send,
+{
+    inner: Arc<crate::Client<C>>,
+    runtime: Runtime,
+}
+
+impl<C: HttpsClient> Client<C>
+where
+    <C as Service<Request<Body>>>::Future: Send,
+{
+    /// Builds `Client` from any `ExactSizeIterator` over Config
+    ///
+    /// # Errors
+    /// Returns an error if the runtime can't be created.
+    pub fn from_configs<Configs>(configs: Configs) -> io::Result<Self>
+    where
+        Configs: Iterator<Item = Config<C>> + ExactSizeIterator,
+    {
+        Self::new(client::Builder::new(), configs)
+    }
+
+    /// Builds `Client` with non-default behaviour of [`client::Builder`]
+    ///
+    /// # Errors
+    /// Returns an error if the runtime can't be created.
+    pub fn new<Configs>(builder: client::Builder, configs: Configs) -> io::Result<Self>
+    where
+        Configs: Iterator<Item = Config<C>> + ExactSizeIterator,
+    {
+        let runtime = runtime::Builder::new_multi_thread().enable_all().build()?;
+        // Workers and scheduler are spawned into the current runtime
+        let inner = runtime.block_on(async { builder.build(configs) });
+
+        Ok(Self {
+            inner: Arc::new(inner),
+            runtime,
+        })
+    }
+
+    /// Takes a snapshot of queue depth and per-worker counters
+    pub fn stats(&self) -> Stats {
+        self.inner.stats()
+    }
+
+    /// Sends [`Method`] and waits for the response
+    ///
+    /// # Errors
+    /// See [`crate::Client::method`].
+    ///
+    /// # Panics
+    /// See [`crate::Client::method`].
+    pub fn method(&self, method: Method) -> Result<Value> {
+        self.runtime.block_on(self.inner.method(method))
+    }
+
+    /// Sends [`Method`] with given [`Options`] and waits for the response
+    ///
+    /// # Errors
+    /// See [`crate::Client::method_with`].
+    ///
+    /// # Panics
+    /// See [`crate::Client::method`].
+    pub fn method_with(&self, method: Method, options: Options) -> Result<Value> {
+        self.runtime.block_on(self.inner.method_with(method, options))
+    }
+
+    /// Sends [`Method`] and deserializes the response into `T`
+    ///
+    /// # Errors
+    /// See [`crate::Client::method`].
+    /// Also returns [`Error::Serialization`] if the response doesn't match `T`.
+    ///
+    /// # Panics
+    /// See [`crate::Client::method`].
+    pub fn method_as<T>(&self, method: Method) -> Result<T>
+    where
+        T: DeserializeOwned,
+    {
+        let value = self.method(method)?;
+        serde_json::from_value(value).map_err(|error| Error::from(Arc::new(error)))
+    }
+
+    /// Sends all methods at once and waits for all responses
+    ///
+    /// Methods are queued together, so they can be compressed into the same executes.
+    /// Responses are in the order of methods.
+    ///
+    /// # Panics
+    /// See [`crate::Client::method`].
+    pub fn methods<I>(&self, methods: I) -> Vec<Result<Value>>
+    where
+        I: IntoIterator<Item = Method>,
+    {
+        let handles: Vec<_> = methods
+            .into_iter()
+            .map(|method| {
+                let inner = self.inner.clone();
+                self.runtime.spawn(async move { inner.method(method).await })
+            })
+            .collect();
+
+        self.runtime.block_on(async {
+            let mut results = Vec::with_capacity(handles.len());
+            for handle in handles {
+                match handle.await {
+                    Ok(result) => results.push(result),
+                    Err(error) => std::panic::resume_unwind(error.into_panic()),
+                }
+            }
+            results
+        })
+    }
+}
+
+impl<C: HttpsClient> std::fmt::Debug for Client<C>
+where
+    <C as Service<Request<Body>>>::Future: Send,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Client").finish_non_exhaustive()
+    }
+}
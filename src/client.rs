@@ -1,23 +1,51 @@
+mod builder;
+mod bulk;
+pub mod cache;
+mod captcha;
+mod coalesce;
+pub mod journal;
+mod lanes;
+pub mod merge;
 mod message;
+mod middleware;
+mod options;
+mod rate;
+mod scheduler;
+pub mod stats;
 mod worker;
 
+pub use builder::Builder;
+pub use bulk::{Bulk, Progress};
+pub use captcha::{Captcha, CaptchaSolver, Solution};
+pub use middleware::Middleware;
+pub use options::{Options, Priority};
+
+use crate::config::Access;
 use crate::Config;
+use cache::{Key, Layer};
+use coalesce::{Inflight, Joined};
 use message::Message;
+use middleware::Stack;
+use scheduler::Scheduler;
+use stats::{QueueDepth, Stats};
 use worker::Worker;
 
-pub type ResultSender = oneshot::Sender<Result<Value>>;
-pub type TaskSender = mpsc::UnboundedSender<Message>;
-pub type TaskReceiver = Arc<Mutex<mpsc::UnboundedReceiver<Message>>>;
+pub(crate) type ResultSender = oneshot::Sender<Result<Value>>;
+pub(crate) type TaskSender = scheduler::Queue;
 
-use crate::Result;
-use vk_method::Method;
+use crate::{Error, ErrorCode, Result, VkError};
+use vk_method::{Method, Params};
 
 use serde_json::value::Value;
 
+use std::collections::hash_map::{Entry, HashMap};
 use std::iter::ExactSizeIterator;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::sync::oneshot;
+
+use futures_util::stream::{Stream, StreamExt};
 
 use http::request::Request;
 use hyper::body::Body;
@@ -25,6 +53,9 @@ use tower::Service;
 
 pub const MAX_METHODS_IN_EXECUTE: u8 = 25;
 
+/// How many times a method is resent with solved captcha before giving up
+pub const MAX_CAPTCHA_ATTEMPTS: u8 = 3;
+
 // https://github.com/rust-lang/rust/issues/41517#issuecomment-1100644808
 pub trait HttpsClient:
     Service<Request<Body>, Response = http::Response<Body>, Error = hyper::Error>
@@ -46,9 +77,23 @@ where
 {
 }
 
-pub type HyperClient = hyper::client::Client<HttpsConnector<HttpConnector>>;
-use hyper::client::HttpConnector;
-use hyper_tls::HttpsConnector;
+/// Default http client. Its TLS backend is chosen by `native-tls` or `rustls` feature
+#[cfg(feature = "rustls")]
+pub type HyperClient = hyper::client::Client<hyper_rustls::HttpsConnector<Connector>>;
+/// Default http client. Its TLS backend is chosen by `native-tls` or `rustls` feature
+#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+pub type HyperClient = hyper::client::Client<hyper_tls::HttpsConnector<Connector>>;
+use crate::config::Connector;
+
+/// Queue of workers with the same tag and token access
+///
+/// Methods go to routes only when some workers can't run them,
+/// otherwise they are sent to the queue shared by all workers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Route {
+    tag: Option<String>,
+    access: Access,
+}
 
 /// An asynchronous `Client` to make VK Requests with.
 pub struct Client<C: HttpsClient = HyperClient>
@@ -56,29 +101,121 @@ where
     <C as Service<Request<Body>>>::Future: Send,
 {
     sender: TaskSender,
-    #[allow(dead_code)]
+    routes: Vec<(Route, TaskSender)>,
+    next_route: AtomicUsize,
+    queue_depth: Arc<QueueDepth>,
+    cache: Option<Layer>,
+    inflight: Option<Inflight>,
+    captcha_solver: Option<Box<dyn CaptchaSolver>>,
+    middleware: Stack,
     workers: Vec<Worker<C>>,
 }
 
+impl Client<HyperClient> {
+    /// Creates a [`Builder`] to configure a [`Client`]
+    #[must_use]
+    pub fn builder() -> Builder {
+        Builder::new()
+    }
+}
+
 impl<C: HttpsClient> Client<C>
 where
     <C as Service<Request<Body>>>::Future: Send,
 {
     /// Builds `Client` from any `ExactSizeIterator` over Config
+    ///
+    /// Use [`Builder`] to customize the client.
     pub fn from_configs<Configs>(configs: Configs) -> Self
     where
         Configs: Iterator<Item = Config<C>> + ExactSizeIterator,
     {
+        Builder::new().build(configs)
+    }
+
+    fn new<Configs>(mut builder: Builder, configs: Configs) -> Self
+    where
+        Configs: Iterator<Item = Config<C>> + ExactSizeIterator,
+    {
+        let configs: Vec<Config<C>> = configs.collect();
         let mut workers = Vec::with_capacity(configs.len());
 
-        let (sender, receiver) = mpsc::unbounded_channel();
-        let receiver = Arc::new(Mutex::new(receiver));
+        // Queue 0 is shared by all workers, the rest belong to routes
+        let mut routes = Vec::new();
+        let mut route_queues = HashMap::new();
+
+        for config in &configs {
+            let access = config.access();
+            let tags = config.tags.iter().cloned().map(Some).chain([None]);
+
+            for tag in tags {
+                let route = Route {
+                    tag,
+                    access: access.clone(),
+                };
+
+                if let Entry::Vacant(entry) = route_queues.entry(route.clone()) {
+                    entry.insert(routes.len() + 1);
+                    routes.push(route);
+                }
+            }
+        }
+
+        let cache = builder.cache_layer(&configs);
+
+        let (scheduler, mut queues, handles) = Scheduler::new(routes.len() + 1);
+        let sender = queues.remove(0);
+        let routes = routes.into_iter().zip(queues).collect();
+        let queue_depth = Arc::new(QueueDepth::new(0));
 
         for (index, config) in configs.into_iter().enumerate() {
-            workers.push(Worker::new(index, config, receiver.clone()));
+            // Pinned methods go first, shared queue is the last
+            let access = config.access();
+            let tags = config.tags.iter().cloned().map(Some).chain([None]);
+
+            let mut queues: Vec<usize> = tags
+                .map(|tag| {
+                    route_queues[&Route {
+                        tag,
+                        access: access.clone(),
+                    }]
+                })
+                .collect();
+            queues.push(0);
+
+            workers.push(Worker::new(
+                index,
+                config,
+                handles.handle(queues),
+                queue_depth.clone(),
+                builder.merger.clone(),
+            ));
+        }
+
+        tokio::spawn(scheduler.run());
+
+        Self {
+            sender,
+            routes,
+            next_route: AtomicUsize::new(0),
+            queue_depth,
+            cache,
+            inflight: builder.coalesce.then(Inflight::default),
+            captcha_solver: builder.captcha_solver.take(),
+            middleware: std::mem::take(&mut builder.middleware),
+            workers,
         }
+    }
 
-        Self { sender, workers }
+    /// Takes a snapshot of queue depth and per-worker counters
+    ///
+    /// Counters are also reported to the [`metrics`](https://docs.rs/metrics) recorder
+    /// when `metrics` feature is enabled.
+    pub fn stats(&self) -> Stats {
+        Stats {
+            queue_depth: self.queue_depth.load(Ordering::Relaxed),
+            workers: self.workers.iter().map(Worker::stats).collect(),
+        }
     }
 
     /// Asynchronously sends [`Method`]
@@ -121,24 +258,259 @@ where
     /// ```
     /// # Errors
     /// If this function encounters any form of network, serialization or VK error, an error variant will be returned.
+    /// Returns [`Error::InvalidConfig`] if `api_url` of a [`Config`] constructed by hand isn't a url.
     ///
     /// # Panics
     ///
     /// If the method name starts with `execute`, the function will panic.
     /// `Client` itself creates execute requests, so you don't need to use it explicitly.
     pub async fn method(&self, method: Method) -> Result<Value> {
+        self.method_with(method, Options::default()).await
+    }
+
+    /// Asynchronously sends [`Method`] with given [`Options`]
+    ///
+    /// # Example:
+    ///
+    /// ```rust
+    /// use vk_executive::client::{Options, Priority};
+    /// use vk_executive::{Client, Method};
+    /// use vk_method::Params;
+    /// #
+    /// # async fn run(pool: Client) {
+    ///
+    /// let response = pool.method_with(
+    ///     Method::new("users.get", Params::new()),
+    ///     Options::new().priority(Priority::High),
+    /// ).await;
+    /// # }
+    /// ```
+    /// # Errors
+    /// See [`Client::method`].
+    /// Also returns [`Error::NoSuitableWorker`] if no config has tag of the method
+    /// or no token of suitable [kind](crate::config::Builder::kind) is able to call it.
+    ///
+    /// # Panics
+    /// See [`Client::method`]
+    pub async fn method_with(&self, mut method: Method, options: Options) -> Result<Value> {
         assert!(
             !method.name.starts_with("execute"),
             "Execute method is not allowed"
         );
-        let (oneshot_sender, oneshot_receiver) = oneshot::channel();
 
-        self.sender
-            .send(Message::NewMethod(method, oneshot_sender))
+        // Params set by middleware are part of cache and coalescing keys
+        self.middleware.before(&mut method);
+
+        let name = method.name.clone();
+        let result = self.resolve(method, &options).await;
+        self.middleware.after(&name, &result);
+
+        result
+    }
+
+    /// Answers method from the cache or by workers
+    async fn resolve(&self, method: Method, options: &Options) -> Result<Value> {
+        let queue = self.route(&method, options)?;
+
+        let Some(cache) = &self.cache else {
+            return self.call(method, queue, options).await;
+        };
+
+        let Some((key, ttl)) = cache.policy(&method, options.tag.as_deref()) else {
+            return self.call(method, queue, options).await;
+        };
+
+        if let Some(value) = cache.store.get(&key) {
+            return Ok(value);
+        }
+
+        let value = self.call(method, queue, options).await?;
+        cache.store.insert(key, value.clone(), ttl);
+
+        Ok(value)
+    }
+
+    /// Sends stream of [`Method`]s, see [`Client::methods_with`]
+    pub fn methods<'a, S>(&'a self, methods: S) -> impl Stream<Item = (usize, Result<Value>)> + 'a
+    where
+        S: Stream<Item = Method> + 'a,
+    {
+        self.methods_with(methods, Bulk::default())
+    }
+
+    /// Sends stream of [`Method`]s keeping at most [`Bulk::in_flight`] of them unanswered
+    ///
+    /// Yields index of the method in `methods` with its result.
+    /// Methods are taken from `methods` only when there is room for them,
+    /// so the whole batch is never held in memory.
+    ///
+    /// # Example:
+    ///
+    /// ```rust
+    /// use futures::{stream, StreamExt};
+    /// use vk_executive::client::Bulk;
+    /// use vk_executive::{Client, Method};
+    /// use vk_method::Params;
+    /// #
+    /// # async fn run(pool: Client) {
+    ///
+    /// let methods = stream::iter(1..=1000).map(|user_id| {
+    ///     let mut params = Params::new();
+    ///     params.insert("user_id", user_id);
+    ///     Method::new("users.get", params)
+    /// });
+    ///
+    /// let bulk = Bulk::new()
+    ///     .in_flight(100)
+    ///     .on_progress(|progress| eprintln!("{} done", progress.completed));
+    ///
+    /// let mut results = pool.methods_with(methods, bulk);
+    /// while let Some((index, result)) = results.next().await {
+    ///     println!("{index}: {result:?}");
+    /// }
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    /// See [`Client::method`]
+    pub fn methods_with<'a, S>(
+        &'a self,
+        methods: S,
+        bulk: Bulk,
+    ) -> impl Stream<Item = (usize, Result<Value>)> + 'a
+    where
+        S: Stream<Item = Method> + 'a,
+    {
+        bulk.drive(
+            methods.enumerate(),
+            move |(index, method), options| async move {
+                (index, self.method_with(method, options).await)
+            },
+            |(_, result)| Some(result),
+        )
+    }
+
+    /// Chooses queue of workers able to run the method
+    fn route(&self, method: &Method, options: &Options) -> Result<&TaskSender> {
+        let mut candidates = 0;
+
+        let eligible: Vec<&TaskSender> = self
+            .routes
+            .iter()
+            .filter(|(route, _)| route.tag == options.tag)
+            .inspect(|_| candidates += 1)
+            .filter(|(route, _)| route.access.allows(&method.name))
+            .map(|(_, sender)| sender)
+            .collect();
+
+        if options.tag.is_none() && eligible.len() == candidates {
+            return Ok(&self.sender);
+        }
+
+        if eligible.is_empty() {
+            let reason = match &options.tag {
+                Some(tag) if candidates == 0 => format!("no config has tag `{tag}`"),
+                Some(tag) => format!("no token with tag `{tag}` can call `{}`", method.name),
+                None => format!("no token can call `{}`", method.name),
+            };
+
+            return Err(Error::NoSuitableWorker(reason));
+        }
+
+        let next = self.next_route.fetch_add(1, Ordering::Relaxed);
+        Ok(eligible[next % eligible.len()])
+    }
+
+    /// Sends method and resends it while [`CaptchaSolver`] solves requested captchas
+    async fn call(&self, mut method: Method, queue: &TaskSender, options: &Options) -> Result<Value> {
+        let Some(solver) = &self.captcha_solver else {
+            return self.send(method, queue, options).await;
+        };
+
+        for _ in 0..MAX_CAPTCHA_ATTEMPTS {
+            let result = self.send(copy(&method), queue, options).await;
+
+            let Some(captcha) = result.as_ref().err().and_then(|error| captcha(&method, error)) else {
+                return result;
+            };
+            let sid = captcha.sid.clone();
+
+            let Some(key) = solver.solve(captcha).await else {
+                return result;
+            };
+
+            method
+                .params
+                .0
+                .retain(|(name, _)| name != "captcha_sid" && name != "captcha_key");
+            method.params.insert("captcha_sid", sid);
+            method.params.insert("captcha_key", key);
+        }
+
+        self.send(method, queue, options).await
+    }
+
+    /// Sends method to workers and waits for result
+    async fn send(&self, method: Method, queue: &TaskSender, options: &Options) -> Result<Value> {
+        let Some(inflight) = &self.inflight else {
+            let (oneshot_sender, oneshot_receiver) = oneshot::channel();
+            self.enqueue(method, oneshot_sender, queue, options);
+            return oneshot_receiver.await.unwrap();
+        };
+
+        let key = Key::new(&method).scoped(options.tag.as_deref());
+
+        loop {
+            let (oneshot_sender, oneshot_receiver) = oneshot::channel();
+
+            match inflight.join(key.clone(), options.priority, oneshot_sender) {
+                Joined::Leader(leader) => {
+                    let (leader_sender, leader_receiver) = oneshot::channel();
+                    self.enqueue(method, leader_sender, queue, options);
+                    leader.complete(leader_receiver);
+
+                    return oneshot_receiver.await.unwrap();
+                }
+                Joined::Follower => {
+                    if let Ok(result) = oneshot_receiver.await {
+                        return result;
+                    }
+                    // Method of the leader was lost, the first follower to join again leads
+                }
+            }
+        }
+    }
+
+    fn enqueue(&self, method: Method, sender: ResultSender, queue: &TaskSender, options: &Options) {
+        stats::queue_depth(self.queue_depth.fetch_add(1, Ordering::Relaxed) + 1);
+        queue
+            .send(Message::NewMethod(method, sender), options.priority)
             .unwrap();
+    }
+}
+
+/// Copies [`Method`], which doesn't implement `Clone`
+fn copy(method: &Method) -> Method {
+    Method::new(&method.name, Params(method.params.0.clone()))
+}
+
+/// Extracts captcha from error 14 "Captcha needed"
+fn captcha(method: &Method, error: &Error) -> Option<Captcha> {
+    let error: &VkError = match error {
+        Error::VK(error) => error,
+        Error::SharedVK(error) => error,
+        _ => return None,
+    };
 
-        oneshot_receiver.await.unwrap()
+    if error.error_code != ErrorCode::CaptchaNeeded {
+        return None;
     }
+
+    Some(Captcha {
+        method: method.name.clone(),
+        sid: error.captcha_sid.clone()?,
+        img: error.captcha_img.clone()?,
+    })
 }
 
 #[cfg(feature = "thisvk")]
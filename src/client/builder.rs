@@ -0,0 +1,175 @@
+use super::cache::{self, Cache, Layer, Profile};
+use super::captcha::CaptchaSolver;
+use super::merge::{Merger, Strategy};
+use super::middleware::{Middleware, Stack};
+use super::{Client, HttpsClient};
+use crate::Config;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use http::request::Request;
+use hyper::body::Body;
+use tower::Service;
+
+/// Builder of [`Client`] with non-default behaviour
+///
+/// # Example:
+/// ```rust
+/// use vk_executive::{client, Client, Config};
+/// use vk_executive::client::Middleware;
+///
+/// struct TestMode;
+///
+/// impl Middleware for TestMode {
+///     fn before(&self, method: &mut vk_executive::Method) {
+///         method.params.insert("test_mode", 1);
+///     }
+/// }
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let configs = Config::from_tokens(["123456789"].into_iter()).unwrap();
+///
+/// let client: Client = client::Builder::new()
+///     .middleware(TestMode)
+///     .build(configs.into_iter());
+/// # }
+/// ```
+#[derive(Default)]
+pub struct Builder {
+    pub(crate) middleware: Stack,
+    pub(crate) cache_store: Option<Box<dyn Cache>>,
+    pub(crate) cache_ttls: HashMap<String, Duration>,
+    pub(crate) coalesce: bool,
+    pub(crate) merger: Merger,
+    pub(crate) captcha_solver: Option<Box<dyn CaptchaSolver>>,
+}
+
+impl Builder {
+    /// Constructs new `Builder`
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a [`Middleware`] to the stack
+    #[must_use]
+    pub fn middleware(mut self, middleware: impl Middleware) -> Self {
+        self.middleware.push(middleware);
+        self
+    }
+
+    /// Caches successful responses of `method` for `ttl`
+    ///
+    /// Responses are stored in [`cache::Lru`] unless [`Builder::cache_store`] is set.
+    ///
+    /// # Example:
+    /// ```rust
+    /// use std::time::Duration;
+    /// use vk_executive::client;
+    ///
+    /// let builder = client::Builder::new()
+    ///     .cache_method("users.get", Duration::from_secs(600))
+    ///     .cache_method("utils.resolveScreenName", Duration::from_secs(3600));
+    /// ```
+    #[must_use]
+    pub fn cache_method(mut self, method: impl ToString, ttl: Duration) -> Self {
+        self.cache_ttls.insert(method.to_string(), ttl);
+        self
+    }
+
+    /// Sets storage for cached responses
+    #[must_use]
+    pub fn cache_store(mut self, store: impl Cache) -> Self {
+        self.cache_store = Some(Box::new(store));
+        self
+    }
+
+    /// Enables coalescing of identical methods
+    ///
+    /// When a method with the same name and params is already queued or in flight,
+    /// the caller waits for its response instead of sending one more method.
+    #[must_use]
+    pub const fn coalesce(mut self, coalesce: bool) -> Self {
+        self.coalesce = coalesce;
+        self
+    }
+
+    /// Enables merging of single-id methods by the given [`Strategy`]
+    ///
+    /// # Example:
+    /// ```rust
+    /// use vk_executive::client::{self, merge::Strategy};
+    ///
+    /// let builder = client::Builder::new()
+    ///     .merge(Strategy::users_get())
+    ///     .merge(Strategy::groups_get_by_id());
+    /// ```
+    #[must_use]
+    pub fn merge(mut self, strategy: Strategy) -> Self {
+        self.merger.push(strategy);
+        self
+    }
+
+    /// Sets solver of captchas requested by VK with error 14
+    #[must_use]
+    pub fn captcha_solver(mut self, solver: impl CaptchaSolver) -> Self {
+        self.captcha_solver = Some(Box::new(solver));
+        self
+    }
+
+    pub(crate) fn cache_layer<C>(&mut self, configs: &[Config<C>]) -> Option<Layer>
+    where
+        C: Service<Request<Body>>,
+    {
+        if self.cache_ttls.is_empty() {
+            return None;
+        }
+
+        let mut profiles: HashMap<Option<String>, Vec<Profile>> = HashMap::new();
+        for config in configs {
+            let profile = Profile::new(config);
+            let tags = config.tags.iter().cloned().map(Some).chain([None]);
+
+            for tag in tags {
+                let distinct = profiles.entry(tag).or_default();
+                if !distinct.contains(&profile) {
+                    distinct.push(profile.clone());
+                }
+            }
+        }
+
+        Some(Layer {
+            store: self
+                .cache_store
+                .take()
+                .unwrap_or_else(|| Box::<cache::Lru>::default()),
+            ttls: std::mem::take(&mut self.cache_ttls),
+            profiles,
+        })
+    }
+
+    /// Builds `Client` from any `ExactSizeIterator` over Config
+    pub fn build<C, Configs>(self, configs: Configs) -> Client<C>
+    where
+        C: HttpsClient,
+        <C as Service<Request<Body>>>::Future: Send,
+        Configs: Iterator<Item = Config<C>> + ExactSizeIterator,
+    {
+        Client::new(self, configs)
+    }
+}
+
+impl std::fmt::Debug for Builder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Builder")
+            .field("middleware", &self.middleware)
+            .field("cache_store", &self.cache_store.as_ref().map(|_| ".."))
+            .field("cache_ttls", &self.cache_ttls)
+            .field("coalesce", &self.coalesce)
+            .field("merger", &self.merger)
+            .field("captcha_solver", &self.captcha_solver.as_ref().map(|_| ".."))
+            .finish()
+    }
+}
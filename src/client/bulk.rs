@@ -0,0 +1,146 @@
+use super::Options;
+use crate::Result;
+
+use futures_util::stream::{Stream, StreamExt};
+use serde_json::Value;
+
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+
+/// Progress of [`Client::methods_with`](super::Client::methods_with)
+/// and [`Journal::run`](super::journal::Journal::run)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// Methods answered so far
+    pub completed: usize,
+    /// Methods answered with an error so far
+    pub failed: usize,
+}
+
+/// Options of sending a stream of methods
+///
+/// # Example:
+/// ```rust
+/// use vk_executive::client::{Bulk, Options, Priority};
+///
+/// let bulk = Bulk::new()
+///     .in_flight(100)
+///     .ordered(true)
+///     .options(Options::new().priority(Priority::Low))
+///     .on_progress(|progress| println!("{} done", progress.completed));
+///
+/// assert_eq!(bulk.in_flight, 100);
+/// ```
+#[derive(Clone)]
+pub struct Bulk {
+    /// Maximum methods queued or running at once
+    pub in_flight: usize,
+    /// Yield results in order of methods instead of order of completion
+    pub ordered: bool,
+    /// Options of every method
+    pub options: Options,
+    /// Called after every answered method
+    pub progress: Option<Arc<dyn Fn(Progress) + Send + Sync>>,
+}
+
+impl Default for Bulk {
+    fn default() -> Self {
+        Self {
+            in_flight: 256,
+            ordered: false,
+            options: Options::default(),
+            progress: None,
+        }
+    }
+}
+
+impl Bulk {
+    /// Constructs default `Bulk`
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets maximum methods queued or running at once. Zero is treated as one
+    #[must_use]
+    pub const fn in_flight(mut self, in_flight: usize) -> Self {
+        self.in_flight = in_flight;
+        self
+    }
+
+    #[must_use]
+    pub const fn ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+        self
+    }
+
+    #[must_use]
+    pub fn options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    #[must_use]
+    pub fn on_progress(mut self, progress: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Runs `call` for every item of `items` keeping at most [`Bulk::in_flight`] of them unfinished
+    ///
+    /// Outputs are yielded as [`Bulk::ordered`] says, and [`Progress`] counts
+    /// method results which `result` finds in them.
+    pub(crate) fn drive<'a, S, F, Fut, R>(
+        self,
+        items: S,
+        mut call: F,
+        result: R,
+    ) -> impl Stream<Item = Fut::Output> + 'a
+    where
+        S: Stream + 'a,
+        F: FnMut(S::Item, Options) -> Fut + 'a,
+        Fut: Future + 'a,
+        R: Fn(&Fut::Output) -> Option<&Result<Value>> + 'a,
+    {
+        let Self {
+            in_flight,
+            ordered,
+            options,
+            progress,
+        } = self;
+
+        let calls = items.map(move |item| call(item, options.clone()));
+
+        let outputs = if ordered {
+            calls.buffered(in_flight.max(1)).left_stream()
+        } else {
+            calls.buffer_unordered(in_flight.max(1)).right_stream()
+        };
+
+        let mut state = Progress::default();
+        outputs.inspect(move |output| {
+            let Some(result) = result(output) else {
+                return;
+            };
+
+            state.completed += 1;
+            state.failed += usize::from(result.is_err());
+
+            if let Some(progress) = &progress {
+                progress(state);
+            }
+        })
+    }
+}
+
+impl fmt::Debug for Bulk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bulk")
+            .field("in_flight", &self.in_flight)
+            .field("ordered", &self.ordered)
+            .field("options", &self.options)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
@@ -0,0 +1,361 @@
+//! Response caching for idempotent methods
+//!
+//! Cached methods are answered by [`Client`](super::Client) itself
+//! and never reach workers, so cache hits don't consume rate budget.
+//! Caching is opt-in and configured per method name with [`Builder::cache_method`](super::Builder::cache_method).
+//! Stored keys also contain the version and default params of configs able to serve the method,
+//! so a store shared by clients of different configs never mixes their responses.
+
+use crate::Config;
+use http::request::Request;
+use hyper::body::Body;
+use serde_json::value::Value;
+use tower::Service;
+use vk_method::Method;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Cache key made of method name and normalized params
+///
+/// Params are sorted by name, so the same set of params always gives the same key.
+///
+/// # Example:
+/// ```rust
+/// use vk_executive::client::cache::Key;
+/// use vk_executive::Method;
+/// use vk_method::{PairsArray, Params};
+///
+/// let first = Method::new(
+///     "users.get",
+///     Params::try_from(PairsArray([("user_id", "1"), ("fields", "sex")])).unwrap(),
+/// );
+/// let second = Method::new(
+///     "users.get",
+///     Params::try_from(PairsArray([("fields", "sex"), ("user_id", "1")])).unwrap(),
+/// );
+///
+/// assert_eq!(Key::new(&first), Key::new(&second));
+/// assert_eq!(Key::new(&first).as_str(), r#"users.get?fields="sex"&user_id="1""#);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Key(String);
+
+impl Key {
+    #[must_use]
+    pub fn new(method: &Method) -> Self {
+        let mut params: Vec<(&str, String)> = method
+            .params
+            .0
+            .iter()
+            .map(|(key, value)| (key.as_str(), serde_json::to_string(value).unwrap()))
+            .collect();
+        params.sort();
+
+        let mut key = method.name.clone();
+        for (index, (name, value)) in params.into_iter().enumerate() {
+            key.push(if index == 0 { '?' } else { '&' });
+            key.push_str(name);
+            key.push('=');
+            key.push_str(&value);
+        }
+
+        Self(key)
+    }
+
+    /// Distinguishes methods called at different versions or with different default params
+    pub(crate) fn profiled(mut self, name: &str, profiles: &[Profile]) -> Self {
+        for (index, profile) in profiles.iter().enumerate() {
+            self.0.push(if index == 0 { '@' } else { '|' });
+            profile.describe(name, &mut self.0);
+        }
+
+        self
+    }
+
+    /// Distinguishes methods pinned to different tokens
+    pub(crate) fn scoped(mut self, tag: Option<&str>) -> Self {
+        if let Some(tag) = tag {
+            self.0.push('#');
+            self.0.push_str(tag);
+        }
+
+        self
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Storage of cached responses
+///
+/// Implement it to keep responses in an external store.
+/// Implementations must not return values older than `ttl` passed to [`Cache::insert`].
+pub trait Cache: Send + Sync + 'static {
+    /// Returns cached response if it's present and not expired
+    fn get(&self, key: &Key) -> Option<Value>;
+
+    /// Stores successful response for `ttl`
+    fn insert(&self, key: Key, value: Value, ttl: Duration);
+}
+
+/// In-memory cache evicting the least recently used entries
+#[derive(Debug)]
+pub struct Lru {
+    capacity: usize,
+    inner: Mutex<LruInner>,
+}
+
+#[derive(Debug, Default)]
+struct LruInner {
+    tick: u64,
+    entries: HashMap<Key, Entry>,
+    /// Keys ordered by last access
+    recency: BTreeMap<u64, Key>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: Value,
+    /// `None` if the entry never expires
+    expires: Option<Instant>,
+    tick: u64,
+}
+
+impl Lru {
+    /// Creates cache holding at most `capacity` responses
+    ///
+    /// # Panics
+    /// If `capacity` is zero
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "Lru capacity must be positive");
+
+        Self {
+            capacity,
+            inner: Mutex::default(),
+        }
+    }
+}
+
+impl Default for Lru {
+    fn default() -> Self {
+        Self::new(10_000)
+    }
+}
+
+impl LruInner {
+    fn touch(&mut self, key: &Key) {
+        self.tick += 1;
+        let tick = self.tick;
+
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.recency.remove(&entry.tick);
+            self.recency.insert(tick, key.clone());
+            entry.tick = tick;
+        }
+    }
+
+    fn remove(&mut self, key: &Key) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.recency.remove(&entry.tick);
+        }
+    }
+}
+
+impl Cache for Lru {
+    fn get(&self, key: &Key) -> Option<Value> {
+        let mut inner = self.inner.lock().unwrap();
+
+        match inner.entries.get(key) {
+            Some(entry) if entry.expires.map_or(true, |expires| expires > Instant::now()) => {
+                let value = entry.value.clone();
+                inner.touch(key);
+                Some(value)
+            }
+            Some(_) => {
+                inner.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: Key, value: Value, ttl: Duration) {
+        let mut inner = self.inner.lock().unwrap();
+
+        inner.remove(&key);
+
+        while inner.entries.len() >= self.capacity {
+            let Some((_, oldest)) = inner.recency.pop_first() else {
+                break;
+            };
+            inner.entries.remove(&oldest);
+        }
+
+        inner.tick += 1;
+        let tick = inner.tick;
+
+        inner.recency.insert(tick, key.clone());
+        inner.entries.insert(
+            key,
+            Entry {
+                value,
+                expires: Instant::now().checked_add(ttl),
+                tick,
+            },
+        );
+    }
+}
+
+/// Version and default params a config calls methods with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Profile {
+    api_version: String,
+    method_versions: HashMap<String, String>,
+    params: BTreeMap<String, String>,
+}
+
+impl Profile {
+    pub fn new<C>(config: &Config<C>) -> Self
+    where
+        C: Service<Request<Body>>,
+    {
+        Self {
+            api_version: config.api_version.clone(),
+            method_versions: config.method_versions.clone(),
+            params: config.params.clone(),
+        }
+    }
+
+    /// Appends version of the method and default params to the key
+    fn describe(&self, name: &str, key: &mut String) {
+        key.push_str("v=");
+        key.push_str(self.method_versions.get(name).unwrap_or(&self.api_version));
+
+        for (param, value) in &self.params {
+            key.push('&');
+            key.push_str(param);
+            key.push('=');
+            key.push_str(value);
+        }
+    }
+}
+
+/// Cache store with ttl of every cached method
+pub(crate) struct Layer {
+    pub store: Box<dyn Cache>,
+    pub ttls: HashMap<String, Duration>,
+    /// Distinct profiles of configs serving each tag, `None` is the shared queue
+    pub profiles: HashMap<Option<String>, Vec<Profile>>,
+}
+
+impl Layer {
+    /// Returns key and ttl if the method should be cached
+    ///
+    /// Methods sent with different `tag` or served by configs
+    /// of different versions or default params get different keys.
+    pub fn policy(&self, method: &Method, tag: Option<&str>) -> Option<(Key, Duration)> {
+        let ttl = self.ttls.get(&method.name)?;
+        let profiles = self
+            .profiles
+            .get(&tag.map(String::from))
+            .map_or(&[][..], Vec::as_slice);
+
+        Some((
+            Key::new(method)
+                .profiled(&method.name, profiles)
+                .scoped(tag),
+            *ttl,
+        ))
+    }
+}
+
+impl fmt::Debug for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Layer").field("ttls", &self.ttls).finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn key(name: &str) -> Key {
+        Key(name.to_string())
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = Lru::new(2);
+        let ttl = Duration::from_secs(60);
+
+        cache.insert(key("a"), json!(1), ttl);
+        cache.insert(key("b"), json!(2), ttl);
+        assert_eq!(cache.get(&key("a")), Some(json!(1)));
+
+        cache.insert(key("c"), json!(3), ttl);
+
+        assert_eq!(cache.get(&key("a")), Some(json!(1)));
+        assert_eq!(cache.get(&key("b")), None);
+        assert_eq!(cache.get(&key("c")), Some(json!(3)));
+    }
+
+    #[test]
+    fn max_ttl_never_expires() {
+        let cache = Lru::new(2);
+
+        cache.insert(key("a"), json!(1), Duration::MAX);
+
+        assert_eq!(cache.get(&key("a")), Some(json!(1)));
+    }
+
+    #[test]
+    fn keys_depend_on_version_and_default_params() {
+        let profile = |version: &str, lang: &str| Profile {
+            api_version: String::from("5.131"),
+            method_versions: HashMap::from([(String::from("users.get"), version.to_string())]),
+            params: BTreeMap::from([(String::from("lang"), lang.to_string())]),
+        };
+        let layer = |profiles| Layer {
+            store: Box::new(Lru::default()),
+            ttls: HashMap::from([(String::from("users.get"), Duration::from_secs(60))]),
+            profiles: HashMap::from([(None, profiles)]),
+        };
+        let method = || Method::new("users.get", vk_method::Params::new());
+
+        let (first, _) = layer(vec![profile("5.199", "en")])
+            .policy(&method(), None)
+            .unwrap();
+        let (second, _) = layer(vec![profile("5.131", "en")])
+            .policy(&method(), None)
+            .unwrap();
+        let (third, _) = layer(vec![profile("5.199", "ru")])
+            .policy(&method(), None)
+            .unwrap();
+
+        assert_eq!(first.as_str(), "users.get@v=5.199&lang=en");
+        assert_ne!(first, second);
+        assert_ne!(first, third);
+    }
+
+    #[test]
+    fn expires() {
+        let cache = Lru::new(2);
+
+        cache.insert(key("a"), json!(1), Duration::ZERO);
+
+        assert_eq!(cache.get(&key("a")), None);
+    }
+}
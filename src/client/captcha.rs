@@ -0,0 +1,45 @@
+use std::future::Future;
+use std::pin::Pin;
+
+/// Captcha VK asks to solve with error 14 "Captcha needed"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Captcha {
+    /// Name of the method that failed
+    pub method: String,
+    /// Captcha id, sent back as `captcha_sid`
+    pub sid: String,
+    /// Url of captcha image
+    pub img: String,
+}
+
+/// Future returned by [`CaptchaSolver::solve`]
+pub type Solution<'a> = Pin<Box<dyn Future<Output = Option<String>> + Send + 'a>>;
+
+/// Solves captchas on behalf of [`Client`](super::Client)
+///
+/// When a method fails with error 14, the client calls the solver
+/// and resends the method with `captcha_sid` and `captcha_key` params.
+/// Returning `None` gives up and the original error is returned to the caller.
+///
+/// # Example:
+/// ```rust
+/// use vk_executive::client::{Captcha, CaptchaSolver, Solution};
+///
+/// struct Operator;
+///
+/// impl CaptchaSolver for Operator {
+///     fn solve(&self, captcha: Captcha) -> Solution<'_> {
+///         Box::pin(async move {
+///             println!("Enter text from {}", captcha.img);
+///
+///             let mut key = String::new();
+///             std::io::stdin().read_line(&mut key).ok()?;
+///             Some(key.trim().to_string())
+///         })
+///     }
+/// }
+/// ```
+pub trait CaptchaSolver: Send + Sync + 'static {
+    /// Returns text from the captcha image
+    fn solve(&self, captcha: Captcha) -> Solution<'_>;
+}
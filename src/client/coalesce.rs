@@ -0,0 +1,121 @@
+use super::cache::Key;
+use super::{Priority, ResultSender};
+use crate::Result;
+
+use serde_json::value::Value;
+use tokio::sync::oneshot;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Waiters of methods that are already queued or in flight
+///
+/// The first caller of a method becomes a leader: its method is sent to workers
+/// and the single response is fanned out to every caller joined in the meantime.
+/// Methods of different priorities are never joined, so a low priority leader
+/// doesn't delay high priority callers.
+#[derive(Debug, Clone, Default)]
+pub struct Inflight(Arc<Mutex<Waiters>>);
+
+type Waiters = HashMap<(Key, Priority), Vec<ResultSender>>;
+
+/// Role of the caller in [`Inflight::join`]
+pub enum Joined {
+    /// Method must be sent, and the result passed to [`Leader::complete`]
+    Leader(Leader),
+    /// Same method is in flight already
+    ///
+    /// If the leading method is lost, the sender is dropped without a result
+    /// and the follower must join again.
+    Follower,
+}
+
+pub struct Leader {
+    key: (Key, Priority),
+    inflight: Inflight,
+}
+
+impl Inflight {
+    /// Registers `sender` as a waiter of method with the given key and priority
+    pub fn join(&self, key: Key, priority: Priority, sender: ResultSender) -> Joined {
+        let mut inflight = self.0.lock().unwrap();
+        let key = (key, priority);
+
+        if let Some(waiters) = inflight.get_mut(&key) {
+            waiters.push(sender);
+            return Joined::Follower;
+        }
+
+        inflight.insert(key.clone(), vec![sender]);
+
+        Joined::Leader(Leader {
+            key,
+            inflight: self.clone(),
+        })
+    }
+}
+
+impl Leader {
+    /// Waits for result of the leading method and sends it to all waiters
+    pub fn complete(self, receiver: oneshot::Receiver<Result<Value>>) {
+        tokio::spawn(async move {
+            let result = receiver.await;
+
+            let waiters = self.inflight.0.lock().unwrap().remove(&self.key);
+
+            // Worker dropped the method, waiters are dropped as well to join again
+            let Ok(result) = result else {
+                return;
+            };
+
+            for waiter in waiters.into_iter().flatten() {
+                // Caller may have stopped waiting
+                let _ = waiter.send(result.clone());
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use vk_method::{Method, Params};
+
+    fn key() -> Key {
+        Key::new(&Method::new("users.get", Params::new()))
+    }
+
+    #[tokio::test]
+    async fn followers_join_again_when_leader_is_lost() {
+        let inflight = Inflight::default();
+
+        let (leader_sender, _leader_receiver) = oneshot::channel();
+        let Joined::Leader(leader) = inflight.join(key(), Priority::Normal, leader_sender) else {
+            panic!("first caller must lead");
+        };
+
+        let (follower_sender, follower_receiver) = oneshot::channel();
+        assert!(matches!(
+            inflight.join(key(), Priority::Normal, follower_sender),
+            Joined::Follower
+        ));
+
+        let (lost_sender, lost_receiver) = oneshot::channel();
+        drop(lost_sender);
+        leader.complete(lost_receiver);
+
+        assert!(follower_receiver.await.is_err());
+
+        let (sender, receiver) = oneshot::channel();
+        let Joined::Leader(leader) = inflight.join(key(), Priority::Normal, sender) else {
+            panic!("follower must lead after the leader is lost");
+        };
+
+        let (result_sender, result_receiver) = oneshot::channel();
+        leader.complete(result_receiver);
+        result_sender.send(Ok(json!(1))).unwrap();
+
+        assert_eq!(receiver.await.unwrap().unwrap(), json!(1));
+    }
+}
@@ -0,0 +1,328 @@
+//! Durable queue of methods in front of [`Client`]
+//!
+//! [`Journal`] is an append-only log of JSON lines next to the crawl.
+//! Every method is written to the log before it is queued
+//! and marked done after its result is accepted by the [`Sink`].
+//! When the process dies, methods left unfinished are sent again
+//! by the next [`Journal::run`] over the same file.
+//!
+//! Delivery is at-least-once: a method answered right before the crash may be delivered twice.
+
+use super::{copy, Bulk, Client, HttpsClient};
+use crate::Result;
+use vk_method::{Method, Params};
+
+use futures_util::stream::{self, Stream, StreamExt};
+use http::request::Request;
+use hyper::body::Body;
+use ijson::IValue;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tower::Service;
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::pin::pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Receives results of journaled methods
+pub trait Sink {
+    /// Takes result of the method with journal `id`
+    ///
+    /// The method is marked done only after `Ok` is returned,
+    /// an error stops [`Journal::run`] and the method is sent again on the next run.
+    ///
+    /// # Errors
+    /// Any error of storing the result.
+    fn deliver(&mut self, id: u64, method: Method, result: Result<Value>) -> io::Result<()>;
+}
+
+impl<F> Sink for F
+where
+    F: FnMut(u64, Method, Result<Value>) -> io::Result<()>,
+{
+    fn deliver(&mut self, id: u64, method: Method, result: Result<Value>) -> io::Result<()> {
+        self(id, method, result)
+    }
+}
+
+#[derive(Serialize)]
+struct Submit<'a> {
+    id: u64,
+    name: &'a str,
+    params: &'a Params,
+}
+
+#[derive(Serialize)]
+struct Done {
+    done: u64,
+}
+
+/// First line of a compacted journal keeping numbering of methods
+#[derive(Serialize)]
+struct Next {
+    next: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    Submit {
+        id: u64,
+        name: String,
+        params: BTreeMap<String, IValue>,
+    },
+    Done {
+        done: u64,
+    },
+    Next {
+        next: u64,
+    },
+}
+
+/// Append-only log of submitted and answered methods
+///
+/// # Example:
+/// ```rust,no_run
+/// use futures::{stream, StreamExt};
+/// use vk_executive::client::{journal::Journal, Bulk};
+/// use vk_executive::{Client, Method};
+/// use vk_method::Params;
+/// #
+/// # async fn run(client: Client) -> std::io::Result<()> {
+///
+/// let journal = Journal::open("crawl.journal")?;
+/// let methods = stream::iter(1..=1_000_000).map(|user_id| {
+///     let mut params = Params::new();
+///     params.insert("user_id", user_id);
+///     Method::new("users.get", params)
+/// });
+///
+/// let mut sink = |id, _method, result| {
+///     println!("{id}: {result:?}");
+///     Ok(())
+/// };
+///
+/// // After restart methods left unfinished are sent first,
+/// // skip the submitted ones with `methods.skip(journal.submitted())`
+/// journal.run(&client, methods, Bulk::new(), &mut sink).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    file: Mutex<File>,
+    next_id: AtomicU64,
+    /// Methods left unfinished by the previous process
+    recovered: Mutex<Vec<(u64, Method)>>,
+}
+
+impl Journal {
+    /// Opens the journal at `path`, creating it if it doesn't exist
+    ///
+    /// Unfinished methods are read to be sent first by [`Journal::run`],
+    /// and the file is compacted to contain only them.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be read or written,
+    /// or it contains a corrupted line other than the last one.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let (pending, next_id) = read(&path)?;
+
+        let mut compacted = path.clone().into_os_string();
+        compacted.push(".compacting");
+        let compacted = PathBuf::from(compacted);
+        {
+            let mut file = File::create(&compacted)?;
+            let mut line = serde_json::to_vec(&Next { next: next_id })?;
+            line.push(b'\n');
+            file.write_all(&line)?;
+            for (&id, method) in &pending {
+                file.write_all(&submit_line(id, method)?)?;
+            }
+            file.sync_all()?;
+        }
+        fs::rename(&compacted, &path)?;
+        sync_dir(&path)?;
+
+        let file = OpenOptions::new().append(true).open(&path)?;
+
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+            next_id: AtomicU64::new(next_id),
+            recovered: Mutex::new(pending.into_iter().collect()),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Count of methods left unfinished by the previous process and not sent yet
+    pub fn recovered(&self) -> usize {
+        self.recovered.lock().unwrap().len()
+    }
+
+    /// Count of methods ever submitted to this journal
+    ///
+    /// Methods are numbered from zero in order of submission,
+    /// so it's the count of methods of a restarted crawl to skip.
+    pub fn submitted(&self) -> u64 {
+        self.next_id.load(Ordering::Relaxed)
+    }
+
+    /// Writes the method to the journal and returns its id
+    ///
+    /// # Errors
+    /// Returns an error if the journal can't be written.
+    pub fn submit(&self, method: &Method) -> io::Result<u64> {
+        let mut file = self.file.lock().unwrap();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        file.write_all(&submit_line(id, method)?)?;
+        Ok(id)
+    }
+
+    /// Marks the method as done
+    ///
+    /// # Errors
+    /// Returns an error if the journal can't be written.
+    pub fn done(&self, id: u64) -> io::Result<()> {
+        let mut line = serde_json::to_vec(&Done { done: id })?;
+        line.push(b'\n');
+        self.file.lock().unwrap().write_all(&line)
+    }
+
+    /// Flushes the journal to disk
+    ///
+    /// Written lines survive a crash of the process without it,
+    /// call it to survive a crash of the machine as well.
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be synced.
+    pub fn sync(&self) -> io::Result<()> {
+        self.file.lock().unwrap().sync_data()
+    }
+
+    /// Sends recovered methods and then `methods` through the `client`
+    ///
+    /// Every method of `methods` is journaled when it's taken from the stream.
+    /// Results are passed to the `sink` as [`Client::methods_with`] yields them.
+    ///
+    /// # Errors
+    /// Returns the first error of writing the journal or of the `sink`.
+    /// Methods sent at that moment stay unfinished in the journal.
+    ///
+    /// # Panics
+    /// See [`Client::method`]
+    pub async fn run<C, S, K>(
+        &self,
+        client: &Client<C>,
+        methods: S,
+        bulk: Bulk,
+        sink: &mut K,
+    ) -> io::Result<()>
+    where
+        C: HttpsClient,
+        <C as Service<Request<Body>>>::Future: Send,
+        S: Stream<Item = Method>,
+        K: Sink + ?Sized,
+    {
+        let recovered = std::mem::take(&mut *self.recovered.lock().unwrap());
+        let submitted = methods.map(|method| Ok((self.submit(&method)?, method)));
+        let entries = stream::iter(recovered.into_iter().map(Ok)).chain(submitted);
+
+        let results = bulk.drive(
+            entries,
+            |entry: io::Result<(u64, Method)>, options| async move {
+                let (id, method) = entry?;
+                let sent = copy(&method);
+                io::Result::Ok((id, sent, client.method_with(method, options).await))
+            },
+            |entry| entry.as_ref().ok().map(|(_, _, result)| result),
+        );
+        let mut results = pin!(results);
+
+        while let Some(entry) = results.next().await {
+            let (id, method, result) = entry?;
+            sink.deliver(id, method, result)?;
+            self.done(id)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn submit_line(id: u64, method: &Method) -> io::Result<Vec<u8>> {
+    let mut line = serde_json::to_vec(&Submit {
+        id,
+        name: &method.name,
+        params: &method.params,
+    })?;
+    line.push(b'\n');
+    Ok(line)
+}
+
+/// Makes the rename of the journal durable by syncing the directory holding it
+#[cfg(unix)]
+fn sync_dir(path: &Path) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    File::open(dir)?.sync_all()
+}
+
+/// Directories can't be opened as files on other platforms
+#[cfg(not(unix))]
+fn sync_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Reads unfinished methods and the next id of the journal
+fn read(path: &Path) -> io::Result<(BTreeMap<u64, Method>, u64)> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok((BTreeMap::new(), 0)),
+        Err(error) => return Err(error),
+    };
+
+    let mut pending = BTreeMap::new();
+    let mut next_id = 0;
+    let mut lines = BufReader::new(file).lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let line = line?;
+
+        let entry = match serde_json::from_str(&line) {
+            Ok(entry) => entry,
+            // The process died while writing the last line
+            Err(_) if lines.peek().is_none() => break,
+            Err(error) => return Err(io::Error::new(io::ErrorKind::InvalidData, error)),
+        };
+
+        match entry {
+            Entry::Submit { id, name, params } => {
+                let mut method = Method::new(name, Params::new());
+                for (key, value) in params {
+                    method.params.insert(key, value);
+                }
+
+                pending.insert(id, method);
+                next_id = next_id.max(id + 1);
+            }
+            Entry::Done { done } => {
+                pending.remove(&done);
+            }
+            Entry::Next { next } => next_id = next_id.max(next),
+        }
+    }
+
+    Ok((pending, next_id))
+}
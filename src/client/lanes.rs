@@ -0,0 +1,165 @@
+use super::Message;
+
+use std::collections::VecDeque;
+
+/// Priority of a queued method
+///
+/// Workers take methods of higher priority first.
+/// To prevent starvation every 8th take prefers [`Priority::Normal`]
+/// and every 32nd take prefers [`Priority::Low`] lane.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+const LANES: usize = 3;
+
+/// Every `NORMAL_SHARE`th take starts from normal lane
+const NORMAL_SHARE: u64 = 8;
+/// Every `LOW_SHARE`th take starts from low lane
+const LOW_SHARE: u64 = 32;
+
+impl Priority {
+    /// Priorities in order of their lanes
+    const LANES: [Self; LANES] = [Self::High, Self::Normal, Self::Low];
+
+    const fn lane(self) -> usize {
+        match self {
+            Self::High => 0,
+            Self::Normal => 1,
+            Self::Low => 2,
+        }
+    }
+}
+
+/// Queue of methods split by priority
+#[derive(Debug, Default)]
+pub struct Lanes {
+    lanes: [VecDeque<Message>; LANES],
+    taken: u64,
+}
+
+impl Lanes {
+    pub fn push(&mut self, message: Message, priority: Priority) {
+        self.lanes[priority.lane()].push_back(message);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lanes.iter().all(VecDeque::is_empty)
+    }
+
+    /// Order in which lanes are checked for the next take
+    const fn order(&self) -> [usize; LANES] {
+        if self.taken % LOW_SHARE == LOW_SHARE - 1 {
+            [2, 0, 1]
+        } else if self.taken % NORMAL_SHARE == NORMAL_SHARE - 1 {
+            [1, 0, 2]
+        } else {
+            [0, 1, 2]
+        }
+    }
+
+    /// Takes the next message with its priority if any
+    pub fn pop(&mut self) -> Option<(Priority, Message)> {
+        for lane in self.order() {
+            if let Some(message) = self.lanes[lane].pop_front() {
+                self.taken = self.taken.wrapping_add(1);
+                return Some((Priority::LANES[lane], message));
+            }
+        }
+
+        None
+    }
+
+    /// Returns taken messages to the heads of their lanes
+    pub fn restore(&mut self, messages: Vec<(Priority, Message)>) {
+        for (priority, message) in messages.into_iter().rev() {
+            self.lanes[priority.lane()].push_front(message);
+        }
+    }
+
+    /// Takes at most `max` messages with their priorities
+    pub fn take(&mut self, max: usize) -> Vec<(Priority, Message)> {
+        let mut messages = Vec::new();
+
+        while messages.len() < max {
+            let Some(message) = self.pop() else {
+                break;
+            };
+            messages.push(message);
+        }
+
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::oneshot;
+    use vk_method::{Method, Params};
+
+    fn message(name: &str) -> Message {
+        let (sender, _) = oneshot::channel();
+        Message::NewMethod(Method::new(name, Params::new()), sender)
+    }
+
+    fn name((_, message): (Priority, Message)) -> String {
+        match message {
+            Message::NewMethod(method, _) => method.name,
+        }
+    }
+
+    #[test]
+    fn drains_in_priority_order() {
+        let mut lanes = Lanes::default();
+
+        lanes.push(message("low"), Priority::Low);
+        lanes.push(message("normal"), Priority::Normal);
+        lanes.push(message("high"), Priority::High);
+
+        assert_eq!(name(lanes.pop().unwrap()), "high");
+        assert_eq!(name(lanes.pop().unwrap()), "normal");
+        assert_eq!(name(lanes.pop().unwrap()), "low");
+        assert!(lanes.pop().is_none());
+    }
+
+    #[test]
+    fn lower_lanes_are_not_starved() {
+        let mut lanes = Lanes::default();
+
+        for _ in 0..LOW_SHARE {
+            lanes.push(message("high"), Priority::High);
+        }
+        lanes.push(message("normal"), Priority::Normal);
+        lanes.push(message("low"), Priority::Low);
+
+        let names: Vec<String> = lanes
+            .take(LOW_SHARE as usize)
+            .into_iter()
+            .map(name)
+            .collect();
+
+        assert_eq!(names[NORMAL_SHARE as usize - 1], "normal");
+        assert_eq!(names[LOW_SHARE as usize - 1], "low");
+    }
+
+    #[test]
+    fn restores_messages_to_their_lanes() {
+        let mut lanes = Lanes::default();
+
+        lanes.push(message("high"), Priority::High);
+        lanes.push(message("low"), Priority::Low);
+        lanes.push(message("later low"), Priority::Low);
+
+        let taken = lanes.take(2);
+        lanes.push(message("later high"), Priority::High);
+        lanes.restore(taken);
+
+        let names: Vec<String> = lanes.take(4).into_iter().map(name).collect();
+        assert_eq!(names, ["high", "later high", "low", "later low"]);
+    }
+}
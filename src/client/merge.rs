@@ -0,0 +1,341 @@
+//! Merging of single-id methods into one id-list method
+//!
+//! For example, `users.get` calls with `user_id=1` and `user_id=2`
+//! are sent as a single `users.get` with `user_ids=1,2`,
+//! and the response array is split back per caller by `id` field.
+//!
+//! VK skips unknown ids of an id list instead of failing the whole method,
+//! so a merged caller whose id has no items gets [`Strategy::missing`] error,
+//! the one it would get calling the method alone, e.g. `[113] Invalid user id`.
+
+use super::cache::Key;
+use super::ResultSender;
+use crate::{Error, ErrorCode, Result, VkError};
+
+use ijson::IValue;
+use serde_json::value::Value;
+use tokio::sync::oneshot;
+use vk_method::{Method, Params};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Describes how methods with the same name are merged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Strategy {
+    /// Name of mergeable method
+    pub method: String,
+    /// Param holding single id, e.g. `user_id`
+    pub single_param: String,
+    /// Param holding comma separated ids, e.g. `user_ids`
+    pub list_param: String,
+    /// Field of response items holding id
+    pub id_field: String,
+    /// Field holding the items when the response is an object, e.g. `groups`
+    ///
+    /// Other fields of the object are sent to every caller as is.
+    /// Responses of other shapes than an array or such object can't be split,
+    /// and every merged caller gets [`Error::Serialization`](crate::Error::Serialization).
+    pub items_field: Option<String>,
+    /// Maximum ids in one method
+    pub max_ids: usize,
+    /// Error of the method called alone with an unknown id
+    ///
+    /// Merged callers whose id has no items get it instead of an empty list.
+    /// `None` leaves them the empty list.
+    pub missing: Option<(ErrorCode, String)>,
+}
+
+impl Strategy {
+    /// Merges `users.get` by `user_id` up to 1000 ids
+    #[must_use]
+    pub fn users_get() -> Self {
+        Self {
+            method: String::from("users.get"),
+            single_param: String::from("user_id"),
+            list_param: String::from("user_ids"),
+            id_field: String::from("id"),
+            items_field: None,
+            max_ids: 1000,
+            missing: Some((ErrorCode::InvalidUserId, String::from("Invalid user id"))),
+        }
+    }
+
+    /// Merges `groups.getById` by `group_id` up to 500 ids
+    ///
+    /// Both the array of old api versions and `{groups, profiles}` object of new ones are split.
+    #[must_use]
+    pub fn groups_get_by_id() -> Self {
+        Self {
+            method: String::from("groups.getById"),
+            single_param: String::from("group_id"),
+            list_param: String::from("group_ids"),
+            id_field: String::from("id"),
+            items_field: Some(String::from("groups")),
+            max_ids: 500,
+            missing: Some((
+                ErrorCode::InvalidParameter,
+                String::from(
+                    "One of the parameters specified was missing or invalid: group_ids is undefined",
+                ),
+            )),
+        }
+    }
+
+    /// Returns key of the rest params if method can be merged
+    fn candidate(&self, method: &Method) -> Option<Key> {
+        let mut id = None;
+        let mut rest = Params::new();
+
+        for (key, value) in &method.params {
+            if *key == self.list_param {
+                return None;
+            } else if *key == self.single_param {
+                id = Some(parse_id(value)?);
+            } else {
+                rest.0.push((key.clone(), value.clone()));
+            }
+        }
+
+        id?;
+
+        Some(Key::new(&Method::new(&method.name, rest)))
+    }
+}
+
+/// Merge strategies of a client
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Merger(HashMap<String, Strategy>);
+
+impl Merger {
+    pub fn push(&mut self, strategy: Strategy) {
+        self.0.insert(strategy.method.clone(), strategy);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Groups methods that are sent as one. Each group holds indices of `methods`
+    pub fn plan<'a>(&self, methods: impl Iterator<Item = &'a Method>) -> Vec<Vec<usize>> {
+        let mut planner = Planner::new(self);
+        for method in methods {
+            planner.push(method);
+        }
+
+        planner.groups
+    }
+
+    /// Merges methods according to [`Merger::plan`]
+    ///
+    /// Results of merged methods are split and sent to original senders by spawned tasks.
+    pub fn merge(&self, batch: Vec<(Method, ResultSender)>) -> Vec<(Method, ResultSender)> {
+        if self.is_empty() {
+            return batch;
+        }
+
+        let plan = self.plan(batch.iter().map(|(method, _)| method));
+
+        if plan.len() == batch.len() {
+            return batch;
+        }
+
+        let mut batch: Vec<Option<(Method, ResultSender)>> = batch.into_iter().map(Some).collect();
+        let mut merged = Vec::with_capacity(plan.len());
+
+        for group in plan {
+            if let [index] = group[..] {
+                merged.push(batch[index].take().unwrap());
+                continue;
+            }
+
+            let members: Vec<(Method, ResultSender)> = group
+                .into_iter()
+                .map(|index| batch[index].take().unwrap())
+                .collect();
+
+            merged.push(self.merge_group(members));
+        }
+
+        merged
+    }
+
+    fn merge_group(&self, members: Vec<(Method, ResultSender)>) -> (Method, ResultSender) {
+        let strategy = &self.0[&members[0].0.name];
+
+        let mut ids: Vec<i64> = Vec::with_capacity(members.len());
+        let mut senders = Vec::with_capacity(members.len());
+        let mut params = Params::new();
+
+        for (index, (method, sender)) in members.into_iter().enumerate() {
+            for (key, value) in method.params {
+                if key == strategy.single_param {
+                    ids.push(parse_id(&value).unwrap());
+                } else if index == 0 {
+                    params.0.push((key, value));
+                }
+            }
+            senders.push(sender);
+        }
+
+        let mut list: Vec<i64> = ids.clone();
+        list.sort_unstable();
+        list.dedup();
+        params.insert(
+            &strategy.list_param,
+            list.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+
+        let (sender, receiver) = oneshot::channel();
+        let method = Method::new(&strategy.method, params);
+        let strategy = strategy.clone();
+
+        tokio::spawn(async move {
+            // Senders are dropped with the lost merged method
+            if let Ok(result) = receiver.await {
+                split(result, &ids, &strategy, senders);
+            }
+        });
+
+        (method, sender)
+    }
+}
+
+/// Running plan of [`Merger::plan`]
+///
+/// Counts methods a batch turns into after merging while methods are added one by one.
+#[derive(Debug)]
+pub(crate) struct Planner<'a> {
+    merger: &'a Merger,
+    groups: Vec<Vec<usize>>,
+    open: HashMap<Key, usize>,
+    pushed: usize,
+}
+
+impl<'a> Planner<'a> {
+    pub fn new(merger: &'a Merger) -> Self {
+        Self {
+            merger,
+            groups: Vec::new(),
+            open: HashMap::new(),
+            pushed: 0,
+        }
+    }
+
+    /// Adds the next method to a group it can be merged into or to a new group
+    pub fn push(&mut self, method: &Method) {
+        let index = self.pushed;
+        self.pushed += 1;
+
+        let candidate = self
+            .merger
+            .0
+            .get(&method.name)
+            .and_then(|strategy| Some((strategy, strategy.candidate(method)?)));
+
+        let Some((strategy, key)) = candidate else {
+            self.groups.push(vec![index]);
+            return;
+        };
+
+        match self.open.get(&key) {
+            Some(&group) if self.groups[group].len() < strategy.max_ids => {
+                self.groups[group].push(index);
+            }
+            _ => {
+                self.open.insert(key, self.groups.len());
+                self.groups.push(vec![index]);
+            }
+        }
+    }
+
+    /// Count of methods added ones turn into after merging
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+}
+
+/// Accepts both numbers and numeric strings
+fn parse_id(value: &IValue) -> Option<i64> {
+    value
+        .to_i64()
+        .or_else(|| value.as_string().and_then(|id| id.parse().ok()))
+}
+
+/// Sends each caller items with its id
+fn split(result: Result<Value>, ids: &[i64], strategy: &Strategy, senders: Vec<ResultSender>) {
+    let response = match result {
+        Ok(response) => response,
+        Err(error) => {
+            for sender in senders {
+                let _ = sender.send(Err(error.clone()));
+            }
+            return;
+        }
+    };
+
+    let items = match (&response, &strategy.items_field) {
+        (Value::Array(items), _) => items,
+        (Value::Object(object), Some(field)) => match object.get(field) {
+            Some(Value::Array(items)) => items,
+            _ => return unsplittable(strategy, senders),
+        },
+        _ => return unsplittable(strategy, senders),
+    };
+
+    for (id, sender) in ids.iter().zip(senders) {
+        let own: Vec<Value> = items
+            .iter()
+            .filter(|item| item.get(&strategy.id_field).and_then(Value::as_i64) == Some(*id))
+            .cloned()
+            .collect();
+
+        if let (true, Some((code, message))) = (own.is_empty(), &strategy.missing) {
+            let _ = sender.send(Err(missing(*code, message)));
+            continue;
+        }
+
+        let own = match (&response, &strategy.items_field) {
+            (Value::Object(object), Some(field)) => {
+                let mut object = object.clone();
+                object.insert(field.clone(), Value::Array(own));
+                Value::Object(object)
+            }
+            _ => Value::Array(own),
+        };
+
+        let _ = sender.send(Ok(own));
+    }
+}
+
+/// Error of a merged caller whose id has no items
+fn missing(code: ErrorCode, message: &str) -> Error {
+    Error::from(VkError {
+        error_code: code,
+        error_msg: message.to_string(),
+        request_params: None,
+        method: None,
+        captcha_sid: None,
+        captcha_img: None,
+        redirect_uri: None,
+        confirmation_text: None,
+        error_text: None,
+        extra: HashMap::new(),
+    })
+}
+
+/// Fails every caller of a merged method with a response of unknown shape
+fn unsplittable(strategy: &Strategy, senders: Vec<ResultSender>) {
+    let error: serde_json::Error = serde::de::Error::custom(format!(
+        "response of merged `{}` is neither an array nor an object with items",
+        strategy.method
+    ));
+    let error = Error::from(Arc::new(error));
+
+    for sender in senders {
+        let _ = sender.send(Err(error.clone()));
+    }
+}
@@ -0,0 +1,75 @@
+use crate::Result;
+use serde_json::value::Value;
+use std::fmt;
+use std::sync::Arc;
+use vk_method::Method;
+
+/// Hook between [`Method`] and its parsed result
+///
+/// Middlewares are registered with [`Builder::middleware`](super::Builder::middleware)
+/// and called in order of registration.
+///
+/// # Example:
+/// ```rust
+/// use vk_executive::client::Middleware;
+/// use vk_executive::{Method, Result};
+/// use serde_json::Value;
+///
+/// struct English;
+///
+/// impl Middleware for English {
+///     fn before(&self, method: &mut Method) {
+///         method.params.insert("lang", "en");
+///     }
+///
+///     fn after(&self, method: &str, result: &Result<Value>) {
+///         if let Err(error) = result {
+///             eprintln!("{method} failed: {error}");
+///         }
+///     }
+/// }
+/// ```
+pub trait Middleware: Send + Sync + 'static {
+    /// Inspects or modifies outgoing method before it is compiled into a request.
+    ///
+    /// Called once per call of [`Client::method_with`](crate::Client::method_with)
+    /// before the method is looked up in the cache, coalesced, merged or batched into `execute`,
+    /// so params inserted here are part of cache keys and are applied to each inner call.
+    fn before(&self, _method: &mut Method) {}
+
+    /// Observes result of a method right before it is returned to the caller
+    ///
+    /// Called once per call of [`Client::method_with`](crate::Client::method_with)
+    /// with the name of the method and its own result, whether it's answered by the cache,
+    /// shared with a coalesced method or split from a merged one.
+    /// A method retried after a solved captcha is observed with its final result only.
+    fn after(&self, _method: &str, _result: &Result<Value>) {}
+}
+
+/// Ordered set of [`Middleware`]s shared by all workers of a client
+#[derive(Clone, Default)]
+pub(crate) struct Stack(Vec<Arc<dyn Middleware>>);
+
+impl Stack {
+    pub fn push(&mut self, middleware: impl Middleware) {
+        self.0.push(Arc::new(middleware));
+    }
+
+    pub fn before(&self, method: &mut Method) {
+        for middleware in &self.0 {
+            middleware.before(method);
+        }
+    }
+
+    pub fn after(&self, method: &str, result: &Result<Value>) {
+        for middleware in &self.0 {
+            middleware.after(method, result);
+        }
+    }
+}
+
+impl fmt::Debug for Stack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Stack({} middlewares)", self.0.len())
+    }
+}
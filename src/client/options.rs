@@ -0,0 +1,45 @@
+pub use super::lanes::Priority;
+
+/// Options of a single method call
+///
+/// # Example:
+/// ```rust
+/// use vk_executive::client::{Options, Priority};
+///
+/// let options = Options::new()
+///     .priority(Priority::High)
+///     .tag("group");
+///
+/// assert_eq!(options.priority, Priority::High);
+/// assert_eq!(options.tag.as_deref(), Some("group"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    pub priority: Priority,
+    /// Run the method only with tokens having this tag
+    pub tag: Option<String>,
+}
+
+impl Options {
+    /// Constructs default `Options`
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets priority of the method in queue
+    #[must_use]
+    pub const fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Pins the method to tokens with the given [tag](crate::config::Builder::tag)
+    ///
+    /// Untagged methods are load-balanced across all tokens.
+    #[must_use]
+    pub fn tag(mut self, tag: impl ToString) -> Self {
+        self.tag = Some(tag.to_string());
+        self
+    }
+}
@@ -0,0 +1,55 @@
+use crate::config::Aimd;
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Time between requests of one worker, adapted by [`Aimd`] if it's set
+#[derive(Debug)]
+pub struct Rate {
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+    id: usize,
+    aimd: Option<Aimd>,
+    interval_micros: AtomicU64,
+}
+
+impl Rate {
+    pub fn new(id: usize, interval: Duration, aimd: Option<Aimd>) -> Self {
+        let interval = aimd.map_or(interval, |aimd| {
+            interval.max(aimd.min_interval).min(aimd.max_interval)
+        });
+
+        Self {
+            id,
+            aimd,
+            interval_micros: AtomicU64::new(micros(interval)),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_micros(self.interval_micros.load(Ordering::Relaxed))
+    }
+
+    /// Records response of a request
+    pub fn observe(&self, rate_limited: bool) {
+        let Some(aimd) = self.aimd else {
+            return;
+        };
+
+        let _ = self
+            .interval_micros
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |interval| {
+                Some(micros(aimd.next(Duration::from_micros(interval), rate_limited)))
+            });
+
+        #[cfg(feature = "metrics")]
+        metrics::gauge!(
+            "vk_executive_time_between_requests_seconds",
+            self.interval().as_secs_f64(),
+            "worker" => self.id.to_string()
+        );
+    }
+}
+
+fn micros(duration: Duration) -> u64 {
+    u64::try_from(duration.as_micros()).unwrap_or(u64::MAX)
+}
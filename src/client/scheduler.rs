@@ -0,0 +1,340 @@
+//! Central scheduler handing queued methods to ready workers
+//!
+//! [`Client`](super::Client) pushes methods and workers ask for them through channels,
+//! so neither side waits for a lock. Only the scheduler task touches the queues.
+//! Idle workers are served in the order they became ready.
+
+use super::lanes::{Lanes, Priority};
+use super::Message;
+
+use tokio::sync::mpsc::{self, error::SendError, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// Method pushed into a queue
+struct Push {
+    queue: usize,
+    priority: Priority,
+    message: Message,
+}
+
+/// Worker request for methods
+enum Request {
+    /// Waits for methods of any of `queues`, preferring the earlier ones
+    Next(Next),
+    /// Takes methods of the queue available right now
+    More {
+        queue: usize,
+        max: usize,
+        reply: oneshot::Sender<Vec<Message>>,
+    },
+}
+
+struct Next {
+    queues: Arc<[usize]>,
+    max: usize,
+    reply: oneshot::Sender<(usize, Vec<Message>)>,
+}
+
+/// Sending half of a queue
+#[derive(Debug, Clone)]
+pub struct Queue {
+    index: usize,
+    sender: UnboundedSender<Push>,
+}
+
+impl Queue {
+    pub fn send(&self, message: Message, priority: Priority) -> Result<(), SendError<Message>> {
+        self.sender
+            .send(Push {
+                queue: self.index,
+                priority,
+                message,
+            })
+            .map_err(|SendError(push)| SendError(push.message))
+    }
+}
+
+/// Creates [`Handle`]s of workers
+pub struct Handles(UnboundedSender<Request>);
+
+impl Handles {
+    /// Handle of worker served from `queues`, the earlier ones are preferred
+    pub fn handle(&self, queues: Vec<usize>) -> Handle {
+        Handle {
+            queues: queues.into(),
+            sender: self.0.clone(),
+        }
+    }
+}
+
+/// Worker side of the scheduler
+pub struct Handle {
+    queues: Arc<[usize]>,
+    sender: UnboundedSender<Request>,
+}
+
+impl Handle {
+    /// Waits for at most `max` methods of one queue
+    ///
+    /// Returns index of the queue and its methods, `None` when the scheduler is stopped.
+    pub async fn next(&self, max: usize) -> Option<(usize, Vec<Message>)> {
+        self.request(max)?.recv().await
+    }
+
+    /// Asks for at most `max` methods of one queue, see [`Handle::next`]
+    ///
+    /// Use it to stop waiting without losing methods, see [`Reply::cancel`].
+    /// Returns `None` when the scheduler is stopped.
+    pub fn request(&self, max: usize) -> Option<Reply> {
+        let (reply, receiver) = oneshot::channel();
+
+        self.sender
+            .send(Request::Next(Next {
+                queues: self.queues.clone(),
+                max,
+                reply,
+            }))
+            .ok()?;
+
+        Some(Reply(receiver))
+    }
+
+    /// Takes at most `max` methods of the queue without waiting
+    ///
+    /// Returns `None` when the scheduler is stopped.
+    pub async fn more(&self, queue: usize, max: usize) -> Option<Vec<Message>> {
+        let (reply, receiver) = oneshot::channel();
+
+        self.sender
+            .send(Request::More { queue, max, reply })
+            .ok()?;
+
+        receiver.await.ok()
+    }
+}
+
+/// Pending reply to [`Handle::request`]
+pub struct Reply(oneshot::Receiver<(usize, Vec<Message>)>);
+
+impl Reply {
+    /// Waits for the methods, `None` when the scheduler is stopped
+    pub async fn recv(&mut self) -> Option<(usize, Vec<Message>)> {
+        (&mut self.0).await.ok()
+    }
+
+    /// Stops waiting and returns methods already sent by the scheduler
+    ///
+    /// Methods the scheduler tries to send later are returned to their queue.
+    pub fn cancel(mut self) -> Option<(usize, Vec<Message>)> {
+        self.0.close();
+        self.0.try_recv().ok()
+    }
+}
+
+pub struct Scheduler {
+    queues: Vec<Lanes>,
+    /// Workers waiting for methods in order they became ready
+    waiting: VecDeque<Next>,
+    pushes: UnboundedReceiver<Push>,
+    requests: UnboundedReceiver<Request>,
+}
+
+impl Scheduler {
+    /// Creates scheduler of `count` queues with their sending halves
+    pub fn new(count: usize) -> (Self, Vec<Queue>, Handles) {
+        let (push_sender, pushes) = mpsc::unbounded_channel();
+        let (request_sender, requests) = mpsc::unbounded_channel();
+
+        let queues = (0..count)
+            .map(|index| Queue {
+                index,
+                sender: push_sender.clone(),
+            })
+            .collect();
+
+        let scheduler = Self {
+            queues: (0..count).map(|_| Lanes::default()).collect(),
+            waiting: VecDeque::new(),
+            pushes,
+            requests,
+        };
+
+        (scheduler, queues, Handles(request_sender))
+    }
+
+    /// Serves workers until all [`Queue`]s are dropped and queued methods are taken
+    pub async fn run(mut self) {
+        let mut open = true;
+
+        loop {
+            tokio::select! {
+                biased;
+
+                push = self.pushes.recv(), if open => match push {
+                    Some(push) => self.push(push),
+                    None => open = false,
+                },
+                request = self.requests.recv() => match request {
+                    Some(Request::Next(next)) => self.next(next),
+                    Some(Request::More { queue, max, reply }) => {
+                        let (priorities, messages) = self.take(queue, max);
+                        if let Err(messages) = reply.send(messages) {
+                            self.restore(queue, priorities, messages);
+                        }
+                    }
+                    None => return,
+                },
+            }
+
+            if !open && self.queues.iter().all(Lanes::is_empty) {
+                return;
+            }
+        }
+    }
+
+    fn push(&mut self, Push { queue, priority, message }: Push) {
+        self.queues[queue].push(message, priority);
+
+        while let Some(position) = self
+            .waiting
+            .iter()
+            .position(|next| next.queues.contains(&queue))
+        {
+            let next = self.waiting.remove(position).unwrap();
+
+            if !next.reply.is_closed() {
+                self.serve(next);
+                return;
+            }
+        }
+    }
+
+    fn next(&mut self, next: Next) {
+        // Workers stop waiting on timeout
+        self.waiting.retain(|next| !next.reply.is_closed());
+
+        if next.queues.iter().any(|&queue| !self.queues[queue].is_empty()) {
+            self.serve(next);
+        } else {
+            self.waiting.push_back(next);
+        }
+    }
+
+    /// Hands methods of the first non-empty queue to the worker
+    fn serve(&mut self, next: Next) {
+        let Some(&queue) = next
+            .queues
+            .iter()
+            .find(|&&queue| !self.queues[queue].is_empty())
+        else {
+            self.waiting.push_back(next);
+            return;
+        };
+
+        let (priorities, messages) = self.take(queue, next.max);
+        if let Err((queue, messages)) = next.reply.send((queue, messages)) {
+            self.restore(queue, priorities, messages);
+        }
+    }
+
+    /// Takes at most `max` messages of the queue with their priorities
+    fn take(&mut self, queue: usize, max: usize) -> (Vec<Priority>, Vec<Message>) {
+        self.queues[queue].take(max).into_iter().unzip()
+    }
+
+    /// Returns messages the worker stopped waiting for to their lanes
+    fn restore(&mut self, queue: usize, priorities: Vec<Priority>, messages: Vec<Message>) {
+        self.queues[queue].restore(priorities.into_iter().zip(messages).collect());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::time::sleep;
+    use vk_method::{Method, Params};
+
+    fn message(name: &str) -> Message {
+        let (sender, _) = oneshot::channel();
+        Message::NewMethod(Method::new(name, Params::new()), sender)
+    }
+
+    fn names(messages: Vec<Message>) -> Vec<String> {
+        messages
+            .into_iter()
+            .map(|Message::NewMethod(method, _)| method.name)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn workers_are_served_in_ready_order() {
+        let (scheduler, queues, handles) = Scheduler::new(2);
+        tokio::spawn(scheduler.run());
+
+        let shared = handles.handle(vec![0]);
+        let pinned = handles.handle(vec![1, 0]);
+
+        let first = tokio::spawn(async move { shared.next(25).await });
+        sleep(Duration::from_millis(10)).await;
+        let second = tokio::spawn(async move { pinned.next(25).await });
+        sleep(Duration::from_millis(10)).await;
+
+        queues[0].send(message("a"), Priority::Normal).unwrap();
+        queues[1].send(message("b"), Priority::Normal).unwrap();
+
+        let (queue, messages) = first.await.unwrap().unwrap();
+        assert_eq!((queue, names(messages)), (0, vec![String::from("a")]));
+
+        let (queue, messages) = second.await.unwrap().unwrap();
+        assert_eq!((queue, names(messages)), (1, vec![String::from("b")]));
+    }
+
+    #[tokio::test]
+    async fn stops_when_queues_are_dropped() {
+        let (scheduler, queues, handles) = Scheduler::new(1);
+        tokio::spawn(scheduler.run());
+        let handle = handles.handle(vec![0]);
+
+        queues[0].send(message("a"), Priority::Normal).unwrap();
+        drop(queues);
+
+        let (_, messages) = handle.next(25).await.unwrap();
+        assert_eq!(names(messages), ["a"]);
+        assert!(handle.next(25).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn cancelled_reply_keeps_sent_methods() {
+        let (scheduler, queues, handles) = Scheduler::new(1);
+        tokio::spawn(scheduler.run());
+        let handle = handles.handle(vec![0]);
+
+        // The scheduler replies right before the worker stops waiting
+        let reply = handle.request(25).unwrap();
+        queues[0].send(message("a"), Priority::Normal).unwrap();
+        sleep(Duration::from_millis(10)).await;
+
+        let (_, messages) = reply.cancel().unwrap();
+        assert_eq!(names(messages), ["a"]);
+    }
+
+    #[tokio::test]
+    async fn cancelled_reply_leaves_methods_queued() {
+        let (scheduler, queues, handles) = Scheduler::new(1);
+        tokio::spawn(scheduler.run());
+        let handle = handles.handle(vec![0]);
+
+        // The worker stops waiting right before the scheduler replies
+        let reply = handle.request(25).unwrap();
+        assert!(reply.cancel().is_none());
+        queues[0].send(message("a"), Priority::Low).unwrap();
+        queues[0].send(message("b"), Priority::High).unwrap();
+
+        let (_, messages) = handle.next(25).await.unwrap();
+        assert_eq!(names(messages), ["b", "a"]);
+    }
+}
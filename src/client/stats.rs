@@ -0,0 +1,187 @@
+use super::MAX_METHODS_IN_EXECUTE;
+use crate::{Error, Result};
+
+use serde_json::value::Value;
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Snapshot of [`Client`](super::Client) statistics
+///
+/// Returned by [`Client::stats`](super::Client::stats).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stats {
+    /// Methods sent to the client but not sent to VK by any worker yet
+    pub queue_depth: usize,
+    /// Statistics of every worker in order of their configs
+    pub workers: Vec<WorkerStats>,
+}
+
+/// Snapshot of single worker statistics
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkerStats {
+    pub id: usize,
+    /// Http requests sent
+    pub requests: u64,
+    /// Methods sent, both standalone and inside `execute`
+    pub methods: u64,
+    /// `batches[n]` is count of requests carrying `n + 1` methods
+    pub batches: [u64; MAX_METHODS_IN_EXECUTE as usize],
+    /// Methods failed because of network errors
+    pub network_errors: u64,
+    /// Methods failed because of VK errors by error code
+    pub vk_errors: HashMap<u16, u64>,
+    /// Total time spent waiting for responses
+    pub latency_total: Duration,
+    /// The slowest response
+    pub latency_max: Duration,
+    /// Current time between requests. Changes over time with [adaptive](crate::config::Builder::adaptive) rate
+    pub time_between_requests: Duration,
+}
+
+impl WorkerStats {
+    /// Average methods per request divided by [`MAX_METHODS_IN_EXECUTE`]
+    ///
+    /// Returns `0.0` if worker haven't sent any request yet.
+    #[must_use]
+    pub fn fill_ratio(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+
+        self.methods as f64 / (self.requests as f64 * f64::from(MAX_METHODS_IN_EXECUTE))
+    }
+
+    /// Average time spent waiting for a response
+    #[must_use]
+    pub fn latency_avg(&self) -> Duration {
+        if self.requests == 0 {
+            return Duration::ZERO;
+        }
+
+        Duration::from_secs_f64(self.latency_total.as_secs_f64() / self.requests as f64)
+    }
+
+    /// Current requests per second, see [`WorkerStats::time_between_requests`]
+    ///
+    /// `None` if requests are sent without delay.
+    #[must_use]
+    pub fn rate(&self) -> Option<f64> {
+        if self.time_between_requests.is_zero() {
+            return None;
+        }
+
+        Some(1.0 / self.time_between_requests.as_secs_f64())
+    }
+}
+
+/// Queue depth shared between [`Client`](super::Client) and workers
+pub type QueueDepth = AtomicUsize;
+
+/// Live counters of one worker
+#[derive(Debug, Default)]
+pub struct Counters {
+    id: usize,
+    requests: AtomicU64,
+    methods: AtomicU64,
+    batches: [AtomicU64; MAX_METHODS_IN_EXECUTE as usize],
+    network_errors: AtomicU64,
+    vk_errors: Mutex<HashMap<u16, u64>>,
+    latency_total_micros: AtomicU64,
+    latency_max_micros: AtomicU64,
+}
+
+impl Counters {
+    pub fn new(id: usize) -> Self {
+        Self {
+            id,
+            ..Self::default()
+        }
+    }
+
+    /// Records request carrying `methods` methods
+    pub fn request(&self, methods: usize) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.methods.fetch_add(methods as u64, Ordering::Relaxed);
+
+        if let Some(batch) = methods.checked_sub(1).and_then(|index| self.batches.get(index)) {
+            batch.fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[cfg(feature = "metrics")]
+        {
+            let worker = self.id.to_string();
+            metrics::increment_counter!("vk_executive_requests_total", "worker" => worker.clone());
+            metrics::counter!("vk_executive_methods_total", methods as u64, "worker" => worker.clone());
+            metrics::histogram!("vk_executive_methods_per_request", methods as f64, "worker" => worker);
+        }
+    }
+
+    /// Records time between sending request and parsing response
+    pub fn latency(&self, latency: Duration) {
+        let micros = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+
+        self.latency_total_micros.fetch_add(micros, Ordering::Relaxed);
+        self.latency_max_micros.fetch_max(micros, Ordering::Relaxed);
+
+        #[cfg(feature = "metrics")]
+        metrics::histogram!(
+            "vk_executive_request_duration_seconds",
+            latency.as_secs_f64(),
+            "worker" => self.id.to_string()
+        );
+    }
+
+    /// Records result of single method
+    pub fn result(&self, result: &Result<Value>) {
+        let code = match result {
+            Ok(_) | Err(Error::NoSuitableWorker(_) | Error::Serialization(_)) => return,
+            Err(Error::InvalidConfig(_)) => return,
+            Err(Error::Network(_)) => {
+                self.network_errors.fetch_add(1, Ordering::Relaxed);
+
+                #[cfg(feature = "metrics")]
+                metrics::increment_counter!(
+                    "vk_executive_network_errors_total",
+                    "worker" => self.id.to_string()
+                );
+
+                return;
+            }
+            Err(Error::VK(error)) => error.error_code.code(),
+            Err(Error::SharedVK(error)) => error.error_code.code(),
+        };
+
+        *self.vk_errors.lock().unwrap().entry(code).or_default() += 1;
+
+        #[cfg(feature = "metrics")]
+        metrics::increment_counter!(
+            "vk_executive_vk_errors_total",
+            "worker" => self.id.to_string(),
+            "code" => code.to_string()
+        );
+    }
+
+    pub fn snapshot(&self) -> WorkerStats {
+        WorkerStats {
+            id: self.id,
+            requests: self.requests.load(Ordering::Relaxed),
+            methods: self.methods.load(Ordering::Relaxed),
+            batches: std::array::from_fn(|index| self.batches[index].load(Ordering::Relaxed)),
+            network_errors: self.network_errors.load(Ordering::Relaxed),
+            vk_errors: self.vk_errors.lock().unwrap().clone(),
+            latency_total: Duration::from_micros(self.latency_total_micros.load(Ordering::Relaxed)),
+            latency_max: Duration::from_micros(self.latency_max_micros.load(Ordering::Relaxed)),
+            time_between_requests: Duration::ZERO,
+        }
+    }
+}
+
+/// Records change of queue depth
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub fn queue_depth(depth: usize) {
+    #[cfg(feature = "metrics")]
+    metrics::gauge!("vk_executive_queue_depth", depth as f64);
+}
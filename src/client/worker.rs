@@ -1,18 +1,25 @@
-use crate::{Result, VkError, VkResult};
+use crate::config::BuildError;
+use crate::{Error, Result, VkError, VkResult};
 use std::result::Result as StdResult;
 
-use super::{Config, HttpsClient, Message, ResultSender, TaskReceiver, MAX_METHODS_IN_EXECUTE};
+use super::merge::{Merger, Planner};
+use super::rate::Rate;
+use super::stats::{self, Counters, QueueDepth, WorkerStats};
+use super::scheduler::Handle;
+use super::{Config, HttpsClient, Message, ResultSender, MAX_METHODS_IN_EXECUTE};
 
-use tokio::sync::mpsc;
 use vk_execute_compiler::ExecuteCompiler;
 
 use serde::Serialize;
 use serde_json::value::Value;
 
+use std::collections::VecDeque;
 use std::marker::PhantomData;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::task::JoinHandle;
-use tokio::time::sleep;
+use tokio::time::{sleep, timeout_at};
 
 use http::request::Request;
 use hyper::body::{to_bytes, Body};
@@ -22,6 +29,34 @@ use url::Url;
 
 use vk_method::{Method, PairsArray, Params};
 
+/// Method with api version it's called at
+type Versioned = (String, Method, ResultSender);
+
+/// Methods of the next request with the count of methods they turn into after merging
+struct Batch<'a> {
+    methods: Vec<(Method, ResultSender)>,
+    merged: Planner<'a>,
+}
+
+impl<'a> Batch<'a> {
+    fn new(merger: &'a Merger) -> Self {
+        Self {
+            methods: Vec::new(),
+            merged: Planner::new(merger),
+        }
+    }
+
+    fn push(&mut self, method: Method, sender: ResultSender) {
+        self.merged.push(&method);
+        self.methods.push((method, sender));
+    }
+
+    /// Count of methods that can be added to the batch
+    fn free(&self) -> usize {
+        MAX_METHODS_IN_EXECUTE as usize - self.merged.len()
+    }
+}
+
 /// One method processing unit based on [`Config`]
 pub struct Worker<C: HttpsClient>
 where
@@ -31,60 +66,253 @@ where
     id: usize,
     #[allow(dead_code)]
     thread: JoinHandle<()>,
+    shared: Arc<Shared>,
     phantom: PhantomData<Config<C>>,
 }
 
+/// State shared between worker loop and spawned request handlers
+struct Shared {
+    counters: Counters,
+    rate: Rate,
+    merger: Merger,
+}
+
+impl Shared {
+    /// Records result and sends it to the caller
+    fn complete(&self, sender: ResultSender, result: Result<Value>) {
+        self.counters.result(&result);
+        // The caller may be gone, e.g. with a dropped stream of `Client::methods_with`
+        let _ = sender.send(result);
+    }
+}
+
 impl<C: HttpsClient> Worker<C>
 where
     <C as Service<Request<Body>>>::Future: Send,
 {
-    pub fn new(id: usize, config: Config<C>, receiver: TaskReceiver) -> Self {
-        let thread = tokio::spawn(async {
-            Self::thread_loop(config, receiver).await;
+    pub fn new(
+        id: usize,
+        config: Config<C>,
+        handle: Handle,
+        queue_depth: Arc<QueueDepth>,
+        merger: Merger,
+    ) -> Self {
+        let shared = Arc::new(Shared {
+            counters: Counters::new(id),
+            rate: Rate::new(id, config.time_between_requests, config.adaptive),
+            merger,
+        });
+
+        let thread = tokio::spawn({
+            let shared = shared.clone();
+
+            async {
+                Self::thread_loop(config, handle, queue_depth, shared).await;
+            }
         });
 
         Self {
             thread,
             id,
+            shared,
             phantom: PhantomData,
         }
     }
 
-    async fn thread_loop(mut config: Config<C>, receiver: TaskReceiver) -> Option<()> {
+    /// Takes a snapshot of worker counters
+    pub fn stats(&self) -> WorkerStats {
+        WorkerStats {
+            time_between_requests: self.shared.rate.interval(),
+            ..self.shared.counters.snapshot()
+        }
+    }
+
+    async fn thread_loop(
+        mut config: Config<C>,
+        handle: Handle,
+        queue_depth: Arc<QueueDepth>,
+        shared: Arc<Shared>,
+    ) -> Option<()> {
+        // Methods taken from queues but left out of a batch because of other api version
+        let mut backlog: VecDeque<Versioned> = VecDeque::new();
+
         loop {
-            let mut receiver = receiver.lock().await;
+            let mut batch = Batch::new(&shared.merger);
 
-            let message = receiver.recv().await?;
+            let (queue, version) = match backlog.pop_front() {
+                Some((version, method, sender)) => {
+                    batch.push(method, sender);
+                    (None, version)
+                }
+                None => {
+                    let (queue, messages) = handle.next(MAX_METHODS_IN_EXECUTE as usize).await?;
+                    let mut methods = Self::accept(messages, &config);
+                    let (version, method, sender) = methods.remove(0);
 
-            match message {
-                Message::NewMethod(method, sender) => {
-                    let mut methods_with_senders =
-                        Self::take_methods(&mut receiver, (MAX_METHODS_IN_EXECUTE - 1) as usize)
-                            .ok()?;
+                    batch.push(method, sender);
+                    Self::sort(methods, &version, &mut batch, &mut backlog);
 
-                    if methods_with_senders.is_empty() {
-                        Self::process_method(&method, sender, &mut config);
-                    } else {
-                        methods_with_senders.push((method, sender));
-                        Self::process_execute(methods_with_senders, &mut config);
+                    (Some(queue), version)
+                }
+            };
+
+            // Postponed methods of the same version go first
+            let mut index = 0;
+            while index < backlog.len() && batch.free() > 0 {
+                if backlog[index].0 == version {
+                    let (_, method, sender) = backlog.remove(index).unwrap();
+                    batch.push(method, sender);
+                } else {
+                    index += 1;
+                }
+            }
+
+            if let Some(queue) = queue {
+                // Merged methods free execute slots, so take more until batch is full.
+                // Postponed methods take the slots as well, otherwise one worker
+                // would drain the queue into its backlog
+                loop {
+                    let free = batch.free().saturating_sub(backlog.len());
+                    if free == 0 {
+                        break;
+                    }
+
+                    let messages = handle.more(queue, free).await?;
+                    if messages.is_empty() {
+                        break;
+                    }
+
+                    let methods = Self::accept(messages, &config);
+                    let postponed = Self::sort(methods, &version, &mut batch, &mut backlog);
+
+                    if shared.merger.is_empty() && !postponed {
+                        break;
                     }
                 }
             }
 
-            //important! Unlock mutex before sleep
-            drop(receiver);
-            sleep(config.time_between_requests).await;
+            if !config.linger.is_zero() {
+                Self::linger(&mut batch, &version, &mut backlog, &handle, &config).await?;
+            }
+
+            // Postponed methods are still queued for the stats
+            let taken = batch.methods.len();
+            stats::queue_depth(queue_depth.fetch_sub(taken, Ordering::Relaxed) - taken);
+
+            let mut batch = shared.merger.merge(batch.methods);
+
+            if batch.len() == 1 {
+                let (method, sender) = batch.pop().unwrap();
+                Self::process_method(method, sender, &version, &mut config, shared.clone());
+            } else {
+                Self::process_execute(batch, &version, &mut config, shared.clone());
+            }
+
+            sleep(shared.rate.interval()).await;
         }
     }
 
+    /// Waits up to [`Config::linger`] until batch has [`Config::min_batch_size`] methods
+    ///
+    /// Returns `None` when the scheduler is stopped.
+    async fn linger(
+        batch: &mut Batch<'_>,
+        version: &str,
+        backlog: &mut VecDeque<Versioned>,
+        handle: &Handle,
+        config: &Config<C>,
+    ) -> Option<()> {
+        let max = MAX_METHODS_IN_EXECUTE as usize;
+        let min_batch_size = config.min_batch_size.min(max);
+        let deadline = Instant::now() + config.linger;
+
+        loop {
+            // Postponed methods take free slots as well
+            let free = batch.free();
+            let room = free.saturating_sub(backlog.len());
+            if max - free >= min_batch_size || room == 0 {
+                return Some(());
+            }
+
+            let mut reply = handle.request(room)?;
+            let (next, expired) = match timeout_at(deadline.into(), reply.recv()).await {
+                Ok(next) => (Some(next?), false),
+                // The scheduler may have replied right at the deadline
+                Err(_) => (reply.cancel(), true),
+            };
+
+            if let Some((_, messages)) = next {
+                let methods = Self::accept(messages, config);
+                Self::sort(methods, version, batch, backlog);
+            }
+
+            if expired {
+                return Some(());
+            }
+        }
+    }
+
+    /// Resolves versions of taken methods
+    fn accept(messages: Vec<Message>, config: &Config<C>) -> Vec<Versioned> {
+        messages
+            .into_iter()
+            .map(|Message::NewMethod(mut method, sender)| {
+                (config.take_version(&mut method), method, sender)
+            })
+            .collect()
+    }
+
+    /// Adds methods of `version` to the batch and postpones the rest
+    ///
+    /// Returns whether any method is postponed.
+    fn sort(
+        methods: Vec<Versioned>,
+        version: &str,
+        batch: &mut Batch<'_>,
+        backlog: &mut VecDeque<Versioned>,
+    ) -> bool {
+        let mut postponed = false;
+
+        for (method_version, method, sender) in methods {
+            if method_version == version {
+                batch.push(method, sender);
+            } else {
+                backlog.push_back((method_version, method, sender));
+                postponed = true;
+            }
+        }
+
+        postponed
+    }
+
     /// Complete single method process up to sending result
-    fn process_method(method: &Method, sender: ResultSender, config: &mut Config<C>) {
-        let request = Self::prepare_request(method, config);
+    fn process_method(
+        mut method: Method,
+        sender: ResultSender,
+        version: &str,
+        config: &mut Config<C>,
+        shared: Arc<Shared>,
+    ) {
+        config.fill_params(&mut method);
+        let request = match Self::prepare_request(&method, version, config) {
+            Ok(request) => request,
+            Err(error) => return shared.complete(sender, Err(error)),
+        };
         let request_future = config.http_client.call(request);
+        shared.counters.request(1);
 
-        tokio::spawn(async {
+        tokio::spawn(async move {
+            let start = Instant::now();
             let result = Self::handle_method(request_future).await;
-            sender.send(result).unwrap();
+            shared.counters.latency(start.elapsed());
+
+            match &result {
+                Err(Error::Network(_)) => {}
+                Err(Error::VK(error)) => shared.rate.observe(error.is_rate_limit()),
+                _ => shared.rate.observe(false),
+            }
+
+            shared.complete(sender, result);
         });
     }
 
@@ -98,42 +326,29 @@ where
         result.map_err(Into::into)
     }
 
-    /// Takes methods from receiver until it becomes empty or reach `max`
-    fn take_methods(
-        receiver: &mut mpsc::UnboundedReceiver<Message>,
-        max: usize,
-    ) -> StdResult<Vec<(Method, ResultSender)>, mpsc::error::TryRecvError> {
-        let mut methods: Vec<(Method, ResultSender)> = Vec::new();
-
-        for _ in 0..max {
-            let message = receiver.try_recv();
-
-            if matches!(message, Err(mpsc::error::TryRecvError::Empty)) {
-                break;
-            }
-
-            match message? {
-                Message::NewMethod(method, sender) => methods.push((method, sender)),
-            }
-        }
-
-        Ok(methods)
-    }
-
-    fn prepare_request(method: &Method, config: &mut Config<C>) -> Request<Body> {
-        let mut url = Url::parse(&format!("{}/method/{}", &config.api_url, method.name)).unwrap();
+    /// Fails if `api_url` of a config constructed by hand isn't a url
+    fn prepare_request(
+        method: &Method,
+        version: &str,
+        config: &mut Config<C>,
+    ) -> Result<Request<Body>> {
+        let api_url = config.api_url.trim_end_matches('/');
+        let invalid = |error: url::ParseError| {
+            BuildError::InvalidApiUrl(config.api_url.clone(), error.to_string())
+        };
+        let mut url = Url::parse(&format!("{api_url}/method/{}", method.name)).map_err(invalid)?;
 
         {
             let mut pairs = url.query_pairs_mut();
-            query(&mut pairs, &[("access_token", &config.token)]);
-            query(&mut pairs, &[("v", &config.api_version)]);
+            query(&mut pairs, &[("access_token", config.token.expose())]);
+            query(&mut pairs, &[("v", version)]);
             query(&mut pairs, &method.params);
         }
 
-        http::Request::post(url.to_string())
+        Ok(http::Request::post(url.to_string())
             .header("Content-Length", 0)
             .body(Body::empty())
-            .unwrap()
+            .unwrap())
     }
 
     /// Makes request and tries to parse response to `Value`
@@ -184,36 +399,65 @@ where
     fn send_execute_results(
         result: Result<Vec<StdResult<Value, crate::VkError>>>,
         senders: Vec<ResultSender>,
+        shared: &Shared,
     ) {
         if let Err(error) = result {
             for sender in senders {
-                sender.send(Err(error.clone())).unwrap();
+                shared.complete(sender, Err(error.clone()));
             }
             return;
         };
 
         for (sender, result) in senders.into_iter().zip(result.unwrap()) {
-            sender.send(result.map_err(Into::into)).unwrap();
+            shared.complete(sender, result.map_err(Into::into));
         }
     }
 
     /// Complete `execute` method process up to sending results
-    fn process_execute(methods_with_senders: Vec<(Method, ResultSender)>, config: &mut Config<C>) {
-        let (methods, senders): (Vec<_>, Vec<_>) = methods_with_senders.into_iter().unzip();
+    fn process_execute(
+        methods_with_senders: Vec<(Method, ResultSender)>,
+        version: &str,
+        config: &mut Config<C>,
+        shared: Arc<Shared>,
+    ) {
+        let (mut methods, senders): (Vec<_>, Vec<_>) = methods_with_senders.into_iter().unzip();
+
+        for method in &mut methods {
+            config.fill_params(method);
+        }
         let execute = ExecuteCompiler::compile(methods);
 
-        let execute = Method::new(
+        let mut execute = Method::new(
             "execute",
             Params::try_from(PairsArray([("code", execute)])).unwrap(),
         );
+        config.fill_params(&mut execute);
 
-        let request = Self::prepare_request(&execute, config);
+        let request = match Self::prepare_request(&execute, version, config) {
+            Ok(request) => request,
+            Err(error) => return Self::send_execute_results(Err(error), senders, &shared),
+        };
 
         let request_future = config.http_client.call(request);
+        shared.counters.request(senders.len());
 
-        tokio::spawn(async {
+        tokio::spawn(async move {
+            let start = Instant::now();
             let result = Self::handle_execute(request_future).await;
-            Self::send_execute_results(result, senders);
+            shared.counters.latency(start.elapsed());
+
+            match &result {
+                Err(Error::Network(_)) => {}
+                Err(Error::SharedVK(error)) => shared.rate.observe(error.is_rate_limit()),
+                Ok(results) => shared.rate.observe(
+                    results
+                        .iter()
+                        .any(|result| result.as_ref().is_err_and(VkError::is_rate_limit)),
+                ),
+                _ => shared.rate.observe(false),
+            }
+
+            Self::send_execute_results(result, senders, &shared);
         });
     }
 
@@ -1,22 +1,53 @@
+mod access;
+mod aimd;
 mod builder;
+mod secret;
+mod settings;
+mod proxy;
+mod transport;
+pub(crate) use access::Access;
+pub use secret::Secret;
+pub use settings::{LoadError, Overrides, Settings, TokenSettings};
+pub use proxy::{Connector, Proxy, ProxyKind};
+pub use transport::Transport;
+pub use access::TokenKind;
+pub use aimd::Aimd;
 pub use builder::{BuildError, Builder};
 use hyper::body::Body;
+use std::collections::{BTreeMap, HashMap};
 use std::time::Duration;
 
 use http::request::Request;
 use tower::Service;
 use crate::client::HyperClient;
+use vk_method::Method;
 
 #[derive(Debug)]
 pub struct Config<C = HyperClient>
 where
     C: Service<Request<Body>>,
 {
-    pub token: String,
+    pub token: Secret,
     pub http_client: C,
     pub api_url: String,
     pub api_version: String,
+    /// Versions of methods called not at [`Config::api_version`], see [`Builder::method_version`]
+    pub method_versions: HashMap<String, String>,
+    /// Params sent with every method, see [`Builder::param`]
+    pub params: BTreeMap<String, String>,
     pub time_between_requests: Duration,
+    /// Adapts time between requests to rate limit errors, see [`Builder::adaptive`]
+    pub adaptive: Option<Aimd>,
+    /// How long a worker waits for more methods to fill a batch, see [`Builder::linger`]
+    pub linger: Duration,
+    /// Batch size a worker waits for during [`Config::linger`]
+    pub min_batch_size: usize,
+    /// Tags used to route methods to this token, see [`Builder::tag`]
+    pub tags: Vec<String>,
+    /// Kind of the token. Unknown kind is allowed to call any method
+    pub kind: Option<TokenKind>,
+    /// Access rights of the token, see [`Builder::scopes`]
+    pub scopes: Option<Vec<String>>,
 }
 
 impl<C> PartialEq for Config<C>
@@ -27,7 +58,15 @@ where
         self.token == other.token
             && self.api_url == other.api_url
             && self.api_version == other.api_version
+            && self.method_versions == other.method_versions
+            && self.params == other.params
             && self.time_between_requests == other.time_between_requests
+            && self.adaptive == other.adaptive
+            && self.linger == other.linger
+            && self.min_batch_size == other.min_batch_size
+            && self.tags == other.tags
+            && self.kind == other.kind
+            && self.scopes == other.scopes
     }
 }
 
@@ -59,8 +98,8 @@ impl Config<HyperClient> {
     /// assert_eq!(
     ///     configs,
     ///     vec![
-    ///         Config::builder().token("123456789".to_string()).build().unwrap(),
-    ///         Config::builder().token("1111".to_string()).build().unwrap()
+    ///         Config::builder().token("123456789").build().unwrap(),
+    ///         Config::builder().token("1111").build().unwrap()
     ///     ]
     /// )
     /// ```
@@ -72,6 +111,89 @@ impl Config<HyperClient> {
         Self::from_tokens_by_prototype(tokens, &Builder::new())
     }
 
+    /// Constructs vector of `Configs` from JSON [`Settings`]
+    ///
+    /// # Errors
+    /// If JSON is invalid or any token is empty
+    ///
+    /// # Example:
+    /// ```rust
+    /// use vk_executive::Config;
+    /// use std::time::Duration;
+    ///
+    /// let configs = Config::from_json(r#"{
+    ///     "api_version": "5.131",
+    ///     "tokens": ["1111", { "token": "2222", "time_between_requests": 50, "tags": ["group"] }]
+    /// }"#).unwrap();
+    ///
+    /// assert_eq!(configs[0].api_version, "5.131");
+    /// assert_eq!(configs[1].time_between_requests, Duration::from_millis(50));
+    /// assert_eq!(configs[1].tags, ["group"]);
+    /// ```
+    pub fn from_json(json: &str) -> Result<Vec<Self>, LoadError> {
+        let settings: Settings = serde_json::from_str(json)?;
+        Ok(settings.into_configs()?)
+    }
+
+    /// Constructs vector of `Configs` from TOML [`Settings`]
+    ///
+    /// # Errors
+    /// If TOML is invalid or any token is empty
+    #[cfg(feature = "toml")]
+    pub fn from_toml(toml: &str) -> Result<Vec<Self>, LoadError> {
+        let settings: Settings = toml::from_str(toml)?;
+        Ok(settings.into_configs()?)
+    }
+
+    /// Constructs vector of `Configs` from environment variables, see [`Settings::from_env`]
+    ///
+    /// # Errors
+    /// If variables are missing or invalid or any token is empty
+    pub fn from_env(prefix: &str) -> Result<Vec<Self>, LoadError> {
+        Ok(Settings::from_env(prefix)?.into_configs()?)
+    }
+}
+
+impl<C> Config<C>
+where
+    C: Service<Request<Body>>,
+{
+    pub(crate) fn access(&self) -> Access {
+        Access::new(self.kind, self.scopes.as_deref())
+    }
+
+    /// Adds default params the method doesn't have
+    pub(crate) fn fill_params(&self, method: &mut Method) {
+        for (key, value) in &self.params {
+            if !method.params.0.iter().any(|(name, _)| name == key) {
+                method.params.insert(key, value.as_str());
+            }
+        }
+    }
+
+    /// Removes `v` param from the method and returns version the method is called at
+    ///
+    /// `v` param takes precedence over [`Config::method_versions`] and [`Config::api_version`].
+    pub(crate) fn take_version(&self, method: &mut Method) -> String {
+        let param = method
+            .params
+            .0
+            .iter()
+            .position(|(key, _)| key == "v")
+            .map(|index| method.params.0.remove(index).1);
+
+        if let Some(version) = param {
+            return version.as_string().map_or_else(
+                || serde_json::to_string(&version).unwrap(),
+                |version| version.to_string(),
+            );
+        }
+
+        self.method_versions
+            .get(&method.name)
+            .unwrap_or(&self.api_version)
+            .clone()
+    }
 }
 
 impl<C> Config<C>
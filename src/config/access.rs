@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+
+/// Type of VK access token
+///
+/// See <https://dev.vk.com/api/access-token/getting-started>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    /// User token can call any method
+    User,
+    /// Community token can call only community-related sections
+    Group,
+    /// Service token of an application can't act on behalf of anybody
+    Service,
+}
+
+/// Sections available to community tokens
+const GROUP_SECTIONS: &[&str] = &[
+    "appWidgets",
+    "board",
+    "docs",
+    "groups",
+    "market",
+    "messages",
+    "photos",
+    "podcasts",
+    "stories",
+    "storage",
+    "users",
+    "utils",
+    "wall",
+];
+
+/// Sections unavailable to service tokens
+const SERVICE_DENIED_SECTIONS: &[&str] = &[
+    "account",
+    "ads",
+    "docs",
+    "fave",
+    "messages",
+    "newsfeed",
+    "notes",
+    "notifications",
+    "stats",
+    "storage",
+];
+
+/// Sections where every method requires access right with the same name
+const SCOPED_SECTIONS: &[&str] = &["ads", "docs", "messages", "notes", "notifications", "stats"];
+
+fn section(method: &str) -> &str {
+    method.split('.').next().unwrap_or(method)
+}
+
+impl TokenKind {
+    /// Whether token of this kind can call the method
+    ///
+    /// # Example:
+    /// ```rust
+    /// use vk_executive::config::TokenKind;
+    ///
+    /// assert!(TokenKind::User.allows("friends.get"));
+    /// assert!(!TokenKind::Group.allows("friends.get"));
+    /// assert!(!TokenKind::Service.allows("messages.send"));
+    /// ```
+    #[must_use]
+    pub fn allows(self, method: &str) -> bool {
+        let section = section(method);
+
+        match self {
+            Self::User => true,
+            Self::Group => GROUP_SECTIONS.contains(&section),
+            Self::Service => !SERVICE_DENIED_SECTIONS.contains(&section),
+        }
+    }
+}
+
+/// What methods a token can call
+///
+/// Tokens without kind and scopes are assumed to be able to call anything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub(crate) struct Access {
+    pub kind: Option<TokenKind>,
+    /// Sorted access rights
+    pub scopes: Option<Vec<String>>,
+}
+
+impl Access {
+    pub fn new(kind: Option<TokenKind>, scopes: Option<&[String]>) -> Self {
+        let scopes = scopes.map(|scopes| {
+            let mut scopes = scopes.to_vec();
+            scopes.sort();
+            scopes.dedup();
+            scopes
+        });
+
+        Self { kind, scopes }
+    }
+
+    pub fn allows(&self, method: &str) -> bool {
+        if let Some(kind) = self.kind {
+            if !kind.allows(method) {
+                return false;
+            }
+        }
+
+        let section = section(method);
+
+        match &self.scopes {
+            Some(scopes) if SCOPED_SECTIONS.contains(&section) => {
+                scopes.iter().any(|scope| scope == section)
+            }
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_access_allows_everything() {
+        let access = Access::default();
+
+        assert!(access.allows("messages.send"));
+        assert!(access.allows("friends.get"));
+    }
+
+    #[test]
+    fn scopes_restrict_scoped_sections() {
+        let access = Access::new(Some(TokenKind::Group), Some(&[String::from("docs")]));
+
+        assert!(access.allows("docs.getUploadServer"));
+        assert!(access.allows("groups.getById"));
+        assert!(!access.allows("messages.send"));
+        assert!(!access.allows("friends.get"));
+    }
+}
@@ -0,0 +1,156 @@
+use super::BuildError;
+
+use std::time::Duration;
+
+/// Adaptive rate control: additive increase, multiplicative decrease
+///
+/// Worker starts at [`Config::time_between_requests`](super::Config::time_between_requests),
+/// raises its rate by [`Aimd::increase`] after every request without rate limit errors
+/// and multiplies the rate by [`Aimd::decrease`] after error 6, 9 or 29.
+/// So each token runs near its real limit. Current rate is shown in
+/// [`WorkerStats::time_between_requests`](crate::client::stats::WorkerStats::time_between_requests).
+///
+/// The learned rate lives as long as the client. Persisting it is left to the caller:
+/// save the stats of each token and pass them as the initial time between requests next time.
+///
+/// # Example:
+/// ```rust,no_run
+/// use vk_executive::config::{self, Aimd};
+/// use vk_executive::{Client, Config};
+/// use std::time::Duration;
+/// #
+/// # fn load() -> Vec<(String, Duration)> { Vec::new() }
+/// # fn save(_: Vec<(String, Duration)>) {}
+///
+/// // Tokens with their time between requests learned by the previous run
+/// let saved = load();
+///
+/// let configs: Vec<Config> = saved
+///     .iter()
+///     .map(|(token, learned)| {
+///         config::Builder::new()
+///             .token(token)
+///             .time_between_requests(*learned)
+///             .adaptive(Aimd::default())
+///             .build()
+///             .unwrap()
+///     })
+///     .collect();
+/// let client = Client::from_configs(configs.into_iter());
+///
+/// // ... before shutdown
+/// let learned = saved
+///     .into_iter()
+///     .zip(client.stats().workers)
+///     .map(|((token, _), stats)| (token, stats.time_between_requests))
+///     .collect();
+/// save(learned);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aimd {
+    /// Time between requests at the highest rate
+    pub min_interval: Duration,
+    /// Time between requests at the lowest rate
+    pub max_interval: Duration,
+    /// Requests per second added to rate after a request without rate limit errors
+    pub increase: f64,
+    /// Factor rate is multiplied by after rate limit error, greater than 0 and at most 1
+    pub decrease: f64,
+}
+
+impl Default for Aimd {
+    /// From 0.5 up to 20 requests per second, +0.05 per request, halved on errors
+    fn default() -> Self {
+        Self {
+            min_interval: Duration::from_millis(50),
+            max_interval: Duration::from_secs(2),
+            increase: 0.05,
+            decrease: 0.5,
+        }
+    }
+}
+
+impl Aimd {
+    /// Time between requests after a request, rate limited or not
+    #[must_use]
+    pub fn next(&self, interval: Duration, rate_limited: bool) -> Duration {
+        let rate = 1.0 / interval.as_secs_f64().max(f64::EPSILON);
+
+        let rate = if rate_limited {
+            rate * self.decrease
+        } else {
+            rate + self.increase
+        };
+
+        Duration::try_from_secs_f64(1.0 / rate)
+            .unwrap_or(self.max_interval)
+            .max(self.min_interval)
+            .min(self.max_interval)
+    }
+
+    /// Checks that intervals are ordered and factors keep rate positive
+    pub(crate) fn validate(&self) -> Result<(), BuildError> {
+        let invalid = |reason: &str| Err(BuildError::InvalidAimd(reason.to_string()));
+
+        if self.min_interval > self.max_interval {
+            return invalid("min_interval is greater than max_interval");
+        }
+        if !(self.increase.is_finite() && self.increase >= 0.0) {
+            return invalid("increase must be a non-negative number");
+        }
+        if !(self.decrease > 0.0 && self.decrease <= 1.0) {
+            return invalid("decrease must be greater than 0 and at most 1");
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn increases_additively_and_decreases_multiplicatively() {
+        let aimd = Aimd {
+            increase: 1.0,
+            ..Aimd::default()
+        };
+
+        let faster = aimd.next(Duration::from_millis(500), false);
+        assert_eq!(faster.as_millis(), 333);
+
+        let slower = aimd.next(Duration::from_millis(500), true);
+        assert_eq!(slower, Duration::from_secs(1));
+
+        assert_eq!(aimd.next(Duration::from_millis(50), false), aimd.min_interval);
+        assert_eq!(aimd.next(Duration::from_secs(2), true), aimd.max_interval);
+    }
+
+    #[test]
+    fn rejects_invalid_factors_and_intervals() {
+        let invalid = [
+            Aimd {
+                decrease: 0.0,
+                ..Aimd::default()
+            },
+            Aimd {
+                increase: -1.0,
+                ..Aimd::default()
+            },
+            Aimd {
+                min_interval: Duration::from_secs(3),
+                ..Aimd::default()
+            },
+        ];
+
+        for aimd in invalid {
+            assert!(matches!(aimd.validate(), Err(BuildError::InvalidAimd(_))));
+            // Configs built by hand skip validation, so it must not panic either
+            let _ = aimd.next(Duration::from_millis(500), true);
+            let _ = aimd.next(Duration::from_millis(500), false);
+        }
+
+        assert_eq!(Aimd::default().validate(), Ok(()));
+    }
+}
@@ -1,7 +1,18 @@
 use thiserror::Error;
 
-#[derive(Error, Debug, PartialEq, Eq)]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum BuildError {
     #[error("missing parameter {0}")]
-    MissingParameter(String)
-}
\ No newline at end of file
+    MissingParameter(String),
+    #[error("invalid api url {0}: {1}")]
+    InvalidApiUrl(String, String),
+    #[error("invalid api version {0}, expected version like 5.131")]
+    InvalidApiVersion(String),
+    #[error("parameter {0} can't have default value")]
+    ReservedParameter(String),
+    #[error("invalid proxy url {0}: {1}")]
+    InvalidProxyUrl(String, String),
+    #[error("invalid adaptive rate: {0}")]
+    InvalidAimd(String),
+}
@@ -0,0 +1,62 @@
+use serde::Deserialize;
+
+use std::fmt;
+
+/// String which is never printed, like access token
+///
+/// Both `Debug` and `Display` print `***`. Use [`Secret::expose`] to get the value.
+///
+/// # Example:
+/// ```rust
+/// use vk_executive::config::Secret;
+///
+/// let token = Secret::from("1234567890abcdef");
+///
+/// assert_eq!(format!("{token:?}"), "***");
+/// assert_eq!(token.expose(), "1234567890abcdef");
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    #[must_use]
+    pub const fn new(secret: String) -> Self {
+        Self(secret)
+    }
+
+    /// Returns the secret value
+    #[must_use]
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<String> for Secret {
+    fn from(secret: String) -> Self {
+        Self(secret)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(secret: &str) -> Self {
+        Self(secret.to_string())
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
@@ -0,0 +1,471 @@
+//! Configs described by files and environment
+//!
+//! Settings hold defaults shared by all tokens and a list of tokens,
+//! each of which may override any default. For example, this TOML
+//! ```toml
+//! api_version = "5.131"
+//! time_between_requests = 50
+//! method_versions = { "messages.send" = "5.199" }
+//! params = { lang = "ru" }
+//!
+//! tokens = [
+//!     "1111",
+//!     { token = "2222", tags = ["group"], kind = "group" },
+//!     { token = "3333", api_version = "5.199", time_between_requests = 334 },
+//! ]
+//! ```
+//! gives three [`Config`]s. `time_between_requests` is given in milliseconds.
+//!
+//! [`Settings`] implements [`Deserialize`], so it can be read from any format serde supports.
+
+mod load_error;
+pub use load_error::LoadError;
+
+use super::{BuildError, Builder, Config, Secret, TokenKind};
+
+use http::request::Request;
+use hyper::body::Body;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use tower::Service;
+
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+/// Defaults and list of tokens
+///
+/// Unknown fields are rejected.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "SettingsEntry")]
+pub struct Settings {
+    /// Fields applied to every token
+    pub defaults: Overrides,
+    pub tokens: Vec<TokenSettings>,
+}
+
+#[derive(Deserialize)]
+struct SettingsEntry {
+    #[serde(flatten)]
+    defaults: Overrides,
+    tokens: Vec<TokenSettings>,
+    /// Fields left by `defaults`, as `deny_unknown_fields` doesn't work with `flatten`
+    #[serde(flatten)]
+    unknown: BTreeMap<String, IgnoredAny>,
+}
+
+impl TryFrom<SettingsEntry> for Settings {
+    type Error = String;
+
+    fn try_from(entry: SettingsEntry) -> Result<Self, Self::Error> {
+        deny_unknown(entry.unknown, "settings")?;
+
+        Ok(Self {
+            defaults: entry.defaults,
+            tokens: entry.tokens,
+        })
+    }
+}
+
+/// Token with its own fields
+///
+/// Deserializes either from a plain token string or from a table with `token` field.
+/// Unknown fields of the table are rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "TokenEntry")]
+pub struct TokenSettings {
+    pub token: Secret,
+    /// Fields overriding [`Settings::defaults`]
+    pub overrides: Overrides,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
+enum TokenEntry {
+    Plain(Secret),
+    Full {
+        token: Secret,
+        #[serde(flatten)]
+        overrides: Overrides,
+        /// Fields left by `overrides`, as `deny_unknown_fields` doesn't work with `flatten`
+        #[serde(flatten)]
+        unknown: BTreeMap<String, IgnoredAny>,
+    },
+}
+
+impl TryFrom<TokenEntry> for TokenSettings {
+    type Error = String;
+
+    fn try_from(entry: TokenEntry) -> Result<Self, Self::Error> {
+        match entry {
+            TokenEntry::Plain(token) => Ok(Self {
+                token,
+                overrides: Overrides::default(),
+            }),
+            TokenEntry::Full {
+                token,
+                overrides,
+                unknown,
+            } => {
+                deny_unknown(unknown, "token")?;
+                Ok(Self { token, overrides })
+            }
+        }
+    }
+}
+
+fn deny_unknown(unknown: BTreeMap<String, IgnoredAny>, of: &str) -> Result<(), String> {
+    match unknown.into_keys().next() {
+        Some(field) => Err(format!("unknown field `{field}` of {of}")),
+        None => Ok(()),
+    }
+}
+
+/// Optional fields of [`Config`]. Missing fields are left as they are in [`Builder`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Overrides {
+    pub api_url: Option<String>,
+    pub api_version: Option<String>,
+    /// Versions of particular methods. Not read from environment
+    pub method_versions: Option<HashMap<String, String>>,
+    /// Params sent with every method. Not read from environment
+    pub params: Option<BTreeMap<String, String>>,
+    /// Deserialized from milliseconds
+    #[serde(default, deserialize_with = "millis")]
+    pub time_between_requests: Option<Duration>,
+    /// Deserialized from milliseconds
+    #[serde(default, deserialize_with = "millis")]
+    pub linger: Option<Duration>,
+    pub min_batch_size: Option<usize>,
+    pub tags: Option<Vec<String>>,
+    pub kind: Option<TokenKind>,
+    pub scopes: Option<Vec<String>>,
+}
+
+fn millis<'de, D>(d: D) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<u64>::deserialize(d)?.map(Duration::from_millis))
+}
+
+impl Overrides {
+    /// Fields of `other` take precedence
+    #[must_use]
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            api_url: other.api_url.or(self.api_url),
+            api_version: other.api_version.or(self.api_version),
+            method_versions: other.method_versions.or(self.method_versions),
+            params: other.params.or(self.params),
+            time_between_requests: other.time_between_requests.or(self.time_between_requests),
+            linger: other.linger.or(self.linger),
+            min_batch_size: other.min_batch_size.or(self.min_batch_size),
+            tags: other.tags.or(self.tags),
+            kind: other.kind.or(self.kind),
+            scopes: other.scopes.or(self.scopes),
+        }
+    }
+
+    /// Sets present fields on the builder
+    #[must_use]
+    pub fn apply<C>(self, mut builder: Builder<C>) -> Builder<C>
+    where
+        C: Service<Request<Body>>,
+    {
+        if let Some(api_url) = self.api_url {
+            builder = builder.api_url(api_url);
+        }
+        if let Some(api_version) = self.api_version {
+            builder = builder.api_version(api_version);
+        }
+        if let Some(method_versions) = self.method_versions {
+            builder.method_versions.clear();
+            for (method, api_version) in method_versions {
+                builder = builder.method_version(method, api_version);
+            }
+        }
+        if let Some(params) = self.params {
+            builder.params = params;
+        }
+        if let Some(time_between_requests) = self.time_between_requests {
+            builder = builder.time_between_requests(time_between_requests);
+        }
+        if let Some(linger) = self.linger {
+            builder.linger = linger;
+        }
+        if let Some(min_batch_size) = self.min_batch_size {
+            builder.min_batch_size = min_batch_size;
+        }
+        if let Some(tags) = self.tags {
+            builder.tags.clear();
+            for tag in tags {
+                builder = builder.tag(tag);
+            }
+        }
+        if let Some(kind) = self.kind {
+            builder = builder.kind(kind);
+        }
+        if let Some(scopes) = self.scopes {
+            builder = builder.scopes(scopes);
+        }
+
+        builder
+    }
+
+    /// Reads fields from `{prefix}API_URL`, `{prefix}API_VERSION`, `{prefix}TIME_BETWEEN_REQUESTS`,
+    /// `{prefix}LINGER`, `{prefix}MIN_BATCH_SIZE`, `{prefix}TAGS`, `{prefix}KIND` and `{prefix}SCOPES`.
+    /// Lists are comma separated
+    fn from_vars(prefix: &str, vars: &HashMap<String, String>) -> Result<Self, LoadError> {
+        let var = |name: &str| {
+            let name = format!("{prefix}{name}");
+            vars.get(&name).map(|value| (name, value.as_str()))
+        };
+        let list = |value: &str| value.split(',').map(str::trim).map(String::from).collect();
+
+        let number = |name: &str| {
+            var(name)
+                .map(|(name, value)| {
+                    value
+                        .parse::<u64>()
+                        .map_err(|error| LoadError::Env(name, error.to_string()))
+                })
+                .transpose()
+        };
+
+        let time_between_requests = number("TIME_BETWEEN_REQUESTS")?.map(Duration::from_millis);
+        let linger = number("LINGER")?.map(Duration::from_millis);
+        let min_batch_size = number("MIN_BATCH_SIZE")?.map(|size| size as usize);
+
+        let kind = var("KIND")
+            .map(|(name, value)| {
+                serde_json::from_value(Value::String(value.to_string()))
+                    .map_err(|error| LoadError::Env(name, error.to_string()))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            api_url: var("API_URL").map(|(_, value)| value.to_string()),
+            api_version: var("API_VERSION").map(|(_, value)| value.to_string()),
+            method_versions: None,
+            params: None,
+            time_between_requests,
+            linger,
+            min_batch_size,
+            tags: var("TAGS").map(|(_, value)| list(value)),
+            kind,
+            scopes: var("SCOPES").map(|(_, value)| list(value)),
+        })
+    }
+}
+
+impl Settings {
+    /// Reads settings from environment variables with names starting with `prefix`
+    ///
+    /// Tokens are comma separated in `{prefix}TOKENS`. Defaults are read from
+    /// `{prefix}API_URL`, `{prefix}API_VERSION`, `{prefix}TIME_BETWEEN_REQUESTS` (milliseconds),
+    /// `{prefix}LINGER` (milliseconds), `{prefix}MIN_BATCH_SIZE`, `{prefix}TAGS`, `{prefix}KIND`
+    /// and `{prefix}SCOPES`.
+    /// Token with index `i` overrides them with the same variables prefixed by `{prefix}TOKEN_{i}_`,
+    /// e.g. `VK_TOKEN_0_API_VERSION`.
+    ///
+    /// Variables with non-unicode names are skipped.
+    ///
+    /// # Errors
+    /// If `{prefix}TOKENS` is missing or any variable is invalid or not unicode
+    pub fn from_env(prefix: &str) -> Result<Self, LoadError> {
+        let vars = std::env::vars_os()
+            .filter_map(|(name, value)| Some((name.into_string().ok()?, value)))
+            .filter(|(name, _)| name.starts_with(prefix))
+            .map(|(name, value)| match value.into_string() {
+                Ok(value) => Ok((name, value)),
+                Err(_) => Err(LoadError::Env(name, String::from("not unicode"))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::from_vars(prefix, vars)
+    }
+
+    /// Same as [`Settings::from_env`], but reads variables from an iterator
+    ///
+    /// # Errors
+    /// If `{prefix}TOKENS` is missing or any variable is invalid
+    ///
+    /// # Example:
+    /// ```rust
+    /// use vk_executive::config::Settings;
+    ///
+    /// let settings = Settings::from_vars(
+    ///     "VK_",
+    ///     [
+    ///         ("VK_TOKENS", "1111,2222"),
+    ///         ("VK_API_VERSION", "5.131"),
+    ///         ("VK_TOKEN_1_API_VERSION", "5.199"),
+    ///     ],
+    /// )
+    /// .unwrap();
+    ///
+    /// let configs = settings.into_configs().unwrap();
+    /// assert_eq!(configs[0].api_version, "5.131");
+    /// assert_eq!(configs[1].api_version, "5.199");
+    /// ```
+    pub fn from_vars<Vars, K, V>(prefix: &str, vars: Vars) -> Result<Self, LoadError>
+    where
+        Vars: IntoIterator<Item = (K, V)>,
+        K: ToString,
+        V: ToString,
+    {
+        let vars: HashMap<String, String> = vars
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+
+        let name = format!("{prefix}TOKENS");
+        let tokens = vars
+            .get(&name)
+            .ok_or_else(|| LoadError::Env(name, String::from("missing")))?;
+
+        let tokens = tokens
+            .split(',')
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .enumerate()
+            .map(|(index, token)| {
+                Ok(TokenSettings {
+                    token: Secret::from(token),
+                    overrides: Overrides::from_vars(&format!("{prefix}TOKEN_{index}_"), &vars)?,
+                })
+            })
+            .collect::<Result<_, LoadError>>()?;
+
+        Ok(Self {
+            defaults: Overrides::from_vars(prefix, &vars)?,
+            tokens,
+        })
+    }
+
+    /// Builds a [`Config`] for each token
+    ///
+    /// # Errors
+    /// If any token is empty, `api_url` isn't a http(s) url, `api_version` or a version
+    /// of `method_versions` isn't like `5.131`, or `params` holds `access_token` or `v`.
+    /// See [`Builder::build`]
+    pub fn into_configs(self) -> Result<Vec<Config>, BuildError> {
+        self.into_configs_by_prototype(&Builder::new())
+    }
+
+    /// Builds a [`Config`] for each token starting from `prototype`
+    ///
+    /// # Errors
+    /// Same as [`Settings::into_configs`], and if `prototype` is given an invalid [`Aimd`](super::Aimd)
+    pub fn into_configs_by_prototype<C>(
+        self,
+        prototype: &Builder<C>,
+    ) -> Result<Vec<Config<C>>, BuildError>
+    where
+        C: Service<Request<Body>> + Clone,
+    {
+        let Self { defaults, tokens } = self;
+
+        tokens
+            .into_iter()
+            .map(|TokenSettings { token, overrides }| {
+                let builder = defaults.clone().merge(overrides).apply(prototype.clone());
+                Builder {
+                    token: Some(token),
+                    ..builder
+                }
+                .build()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_override_defaults() {
+        let settings: Settings = serde_json::from_str(
+            r#"{
+                "api_version": "5.131",
+                "kind": "group",
+                "tokens": [
+                    "1111",
+                    { "token": "2222", "api_version": "5.199", "kind": "user", "scopes": ["messages"] }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let configs = settings.into_configs().unwrap();
+
+        assert_eq!(configs[0].token.expose(), "1111");
+        assert_eq!(configs[0].api_version, "5.131");
+        assert_eq!(configs[0].kind, Some(TokenKind::Group));
+        assert_eq!(configs[1].api_version, "5.199");
+        assert_eq!(configs[1].kind, Some(TokenKind::User));
+        assert_eq!(configs[1].scopes, Some(vec![String::from("messages")]));
+    }
+
+    #[test]
+    fn unknown_fields() {
+        let error =
+            serde_json::from_str::<Settings>(r#"{ "api_vesion": "5.199", "tokens": ["1111"] }"#)
+                .unwrap_err();
+
+        assert!(error.to_string().contains("`api_vesion`"), "{error}");
+    }
+
+    #[test]
+    fn unknown_token_fields() {
+        let error = serde_json::from_str::<Settings>(
+            r#"{ "tokens": [{ "token": "1111", "api_verison": "5.199" }] }"#,
+        )
+        .unwrap_err();
+
+        assert!(error.to_string().contains("`api_verison`"), "{error}");
+    }
+
+    #[test]
+    fn invalid_env() {
+        let error = Settings::from_vars("VK_", [("VK_TOKENS", "1"), ("VK_TOKEN_0_KIND", "bot")]);
+        assert!(matches!(error, Err(LoadError::Env(name, _)) if name == "VK_TOKEN_0_KIND"));
+
+        let error = Settings::from_vars("VK_", [("VK_API_VERSION", "5.131")]);
+        assert!(matches!(error, Err(LoadError::Env(name, _)) if name == "VK_TOKENS"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_unicode_env() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        std::env::set_var("VK_NON_UNICODE_TOKENS", "1111");
+        std::env::set_var(OsStr::from_bytes(b"VK_NON_UNICODE_\xff"), "other");
+        let settings = Settings::from_env("VK_NON_UNICODE_").unwrap();
+        assert_eq!(settings.tokens.len(), 1);
+
+        std::env::set_var("VK_NON_UNICODE_KIND", OsStr::from_bytes(b"\xff"));
+        let error = Settings::from_env("VK_NON_UNICODE_");
+        assert!(matches!(error, Err(LoadError::Env(name, _)) if name == "VK_NON_UNICODE_KIND"));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml() {
+        let configs = Config::from_toml(
+            r#"
+            time_between_requests = 50
+            tokens = ["1111", { token = "2222", tags = ["group"] }]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(configs[0].time_between_requests, Duration::from_millis(50));
+        assert_eq!(configs[1].tags, ["group"]);
+    }
+}
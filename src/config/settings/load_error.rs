@@ -0,0 +1,15 @@
+use super::BuildError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum LoadError {
+    #[error("invalid json: {0}")]
+    Json(#[from] serde_json::Error),
+    #[cfg(feature = "toml")]
+    #[error("invalid toml: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("invalid environment variable {0}: {1}")]
+    Env(String, String),
+    #[error(transparent)]
+    Build(#[from] BuildError),
+}
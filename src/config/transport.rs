@@ -0,0 +1,148 @@
+use super::{Connector, Proxy};
+use crate::client::HyperClient;
+
+use hyper::client::HttpConnector;
+
+use std::time::Duration;
+
+/// Settings of the default [`HyperClient`]
+///
+/// [`hyper::Client`] keeps a connection pool shared by all its clones.
+/// Pass one built client to every config to share the pool between tokens,
+/// [`Config::from_tokens_by_prototype`](super::Config::from_tokens_by_prototype) does so.
+///
+/// # Example:
+/// ```rust
+/// use std::time::Duration;
+/// use vk_executive::config::{self, Transport};
+///
+/// let http_client = Transport::new()
+///     .pool_max_idle_per_host(16)
+///     .connect_timeout(Some(Duration::from_secs(5)))
+///     .nodelay(true)
+///     .build();
+///
+/// let prototype = config::Builder::new().http_client(http_client);
+/// let configs = vk_executive::Config::from_tokens_by_prototype(["1", "2"].into_iter(), &prototype)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transport {
+    /// Speak only HTTP/2
+    ///
+    /// Over `https` it requires `rustls` feature. `native-tls` doesn't negotiate HTTP/2
+    /// with ALPN, so servers such as `api.vk.com` reject the connection.
+    /// Plain `http` works with both.
+    pub http2_only: bool,
+    /// TCP keep-alive interval. `None` disables it
+    pub tcp_keepalive: Option<Duration>,
+    /// How long idle connections are kept in the pool. `None` keeps them forever
+    pub pool_idle_timeout: Option<Duration>,
+    /// Maximum idle connections per host
+    pub pool_max_idle_per_host: usize,
+    /// Timeout of establishing TCP connection. `None` waits as long as OS does
+    pub connect_timeout: Option<Duration>,
+    /// Sets `TCP_NODELAY`
+    pub nodelay: bool,
+    /// Proxy all connections go through. `None` connects directly
+    pub proxy: Option<Proxy>,
+}
+
+impl Default for Transport {
+    /// Same settings as [`hyper::Client::builder`] has
+    fn default() -> Self {
+        Self {
+            http2_only: false,
+            tcp_keepalive: None,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            pool_max_idle_per_host: usize::MAX,
+            connect_timeout: None,
+            nodelay: false,
+            proxy: None,
+        }
+    }
+}
+
+impl Transport {
+    /// Constructs default `Transport`
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets [`Transport::http2_only`], see its requirements
+    #[must_use]
+    pub const fn http2_only(mut self, http2_only: bool) -> Self {
+        self.http2_only = http2_only;
+        self
+    }
+
+    #[must_use]
+    pub const fn tcp_keepalive(mut self, tcp_keepalive: Option<Duration>) -> Self {
+        self.tcp_keepalive = tcp_keepalive;
+        self
+    }
+
+    #[must_use]
+    pub const fn pool_idle_timeout(mut self, pool_idle_timeout: Option<Duration>) -> Self {
+        self.pool_idle_timeout = pool_idle_timeout;
+        self
+    }
+
+    #[must_use]
+    pub const fn pool_max_idle_per_host(mut self, pool_max_idle_per_host: usize) -> Self {
+        self.pool_max_idle_per_host = pool_max_idle_per_host;
+        self
+    }
+
+    #[must_use]
+    pub const fn connect_timeout(mut self, connect_timeout: Option<Duration>) -> Self {
+        self.connect_timeout = connect_timeout;
+        self
+    }
+
+    #[must_use]
+    pub const fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = nodelay;
+        self
+    }
+
+    #[must_use]
+    pub fn proxy(mut self, proxy: Option<Proxy>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    /// Builds a client with its own connection pool
+    #[must_use]
+    pub fn build(&self) -> HyperClient {
+        let mut http = HttpConnector::new();
+        http.enforce_http(false);
+        http.set_keepalive(self.tcp_keepalive);
+        http.set_connect_timeout(self.connect_timeout);
+        http.set_nodelay(self.nodelay);
+
+        hyper::Client::builder()
+            .http2_only(self.http2_only)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .build(https(Connector::new(http, self.proxy.clone())))
+    }
+}
+
+/// Wraps connector with TLS of `rustls`, it takes precedence over `native-tls`
+#[cfg(feature = "rustls")]
+fn https(tcp: Connector) -> hyper_rustls::HttpsConnector<Connector> {
+    hyper_rustls::HttpsConnectorBuilder::new()
+        .with_webpki_roots()
+        .https_or_http()
+        .enable_http1()
+        .enable_http2()
+        .wrap_connector(tcp)
+}
+
+/// Wraps connector with TLS of `native-tls`
+#[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+fn https(tcp: Connector) -> hyper_tls::HttpsConnector<Connector> {
+    hyper_tls::HttpsConnector::new_with_connector(tcp)
+}
@@ -1,10 +1,14 @@
+use crate::config::BuildError;
 use crate::vk_error::VkError;
 use std::sync::Arc;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
 /// The Errors that may occur during processing a [`vk_method::Method`].
+///
+/// New variants may be added in minor releases.
 #[derive(thiserror::Error, Debug, Clone)]
+#[non_exhaustive]
 pub enum Error {
     /// Represents any single VK error
     #[error("VK error({0})")]
@@ -16,9 +20,17 @@ pub enum Error {
     /// Represents any network error
     #[error("Network error({0})")]
     Network(Arc<hyper::Error>),
-    // /// Represents any serialization error
-    // #[error("Serializion error({0})")]
-    // Serialization(Arc<serde_json::Error>),
+    /// Represents a method that can't be run by any worker of the client
+    /// For example: method is sent with a tag that no config has
+    #[error("No suitable worker({0})")]
+    NoSuitableWorker(String),
+    /// Represents a config that [`Builder::build`](crate::config::Builder::build) would reject
+    /// For example: `api_url` of a config constructed by hand isn't a url
+    #[error("Invalid config({0})")]
+    InvalidConfig(BuildError),
+    /// Represents any serialization error
+    #[error("Serialization error({0})")]
+    Serialization(Arc<serde_json::Error>),
 }
 
 impl From<VkError> for Error {
@@ -38,9 +50,15 @@ impl From<Arc<hyper::Error>> for Error {
         Self::Network(error) 
     }
 }
-//
-// impl From<Arc<serde_json::Error>> for Error {
-//     fn from(error: Arc<serde_json::Error>) -> Self {
-//         Self::Serialization(error)
-//     }
-// }
+
+impl From<BuildError> for Error {
+    fn from(error: BuildError) -> Self {
+        Self::InvalidConfig(error)
+    }
+}
+
+impl From<Arc<serde_json::Error>> for Error {
+    fn from(error: Arc<serde_json::Error>) -> Self {
+        Self::Serialization(error)
+    }
+}
@@ -7,9 +7,22 @@
 //! By default, it provides relatively low-level [`Client::method`]
 //! However, there is `thisvk` feature avaible.
 //! Consider using it if you want call vk methods directly from [`Client`]. For details see [thisvk](https://docs.rs/thisvk/0/thisvk/).
+//!
+//! Use [`client::Builder`] to add [`client::Middleware`]s.
+//! Runtime statistics are available through [`Client::stats`].
+//! Enable `metrics` feature to report them into [metrics](https://docs.rs/metrics) recorder as well.
+//! TLS is provided by `native-tls` feature by default. Use `rustls` feature instead
+//! for pure Rust TLS with built-in root certificates, e.g. for static musl builds.
+//! It's also required for [`config::Transport::http2_only`] over `https`.
+//! Enable `blocking` feature to call methods from synchronous code with [`blocking::Client`].
+//! Enable `toml` feature to load [`Config`]s with [`Config::from_toml`].
+//! Enable `cli` feature to build `vk-executive` binary running a single method from the command line.
+
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+compile_error!("Either `native-tls` or `rustls` feature must be enabled");
 
 mod vk_error;
-pub use vk_error::VkError;
+pub use vk_error::{ErrorCode, VkError};
 pub(crate) use vk_error::VkResult;
 
 mod error;
@@ -19,8 +32,11 @@ pub use error::Result;
 pub mod config;
 pub use config::Config;
 
-mod client;
+pub mod client;
 pub use client::Client;
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
+
 pub use vk_method;
 pub use vk_method::Method;
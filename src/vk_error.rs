@@ -1,5 +1,9 @@
+mod error_code;
+pub use error_code::ErrorCode;
+
 use crate::Error;
 use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 use std::result::Result as StdResult;
 use thiserror::Error as ThisError;
@@ -9,14 +13,14 @@ use thiserror::Error as ThisError;
 #[serde(rename_all = "snake_case")]
 pub enum VkResult<T> {
     Response(T),
-    Error(VkError),
+    Error(Box<VkError>),
 }
 
 impl<T> From<VkResult<T>> for StdResult<T, VkError> {
     fn from(value: VkResult<T>) -> Self {
         match value {
             VkResult::Response(response) => Ok(response),
-            VkResult::Error(error) => Err(error),
+            VkResult::Error(error) => Err(*error),
         }
     }
 }
@@ -25,7 +29,7 @@ impl<T> From<VkResult<T>> for StdResult<T, Error> {
     fn from(value: VkResult<T>) -> Self {
         match value {
             VkResult::Response(response) => Ok(response),
-            VkResult::Error(error) => Err(Error::from(error)),
+            VkResult::Error(error) => Err(Error::VK(error)),
         }
     }
 }
@@ -40,32 +44,81 @@ impl<T: std::fmt::Display> std::fmt::Display for VkResult<T> {
 }
 
 /// Represents any valid VK Error
+///
+/// Fields VK sends only with some errors are optional.
+/// Fields unknown to this struct are kept in [`VkError::extra`].
 #[derive(Debug, ThisError, Deserialize, Serialize, PartialEq, Eq, Clone)]
 pub struct VkError {
-    pub error_code: u16,
+    pub error_code: ErrorCode,
     pub error_msg: String,
     #[serde(default)]
     #[serde(deserialize_with = "params_from_pairs")]
     pub request_params: Option<HashMap<String, String>>,
+    /// Method failed inside `execute`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+    /// Captcha id of error 14 "Captcha needed"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(deserialize_with = "string_or_number")]
+    pub captcha_sid: Option<String>,
+    /// Captcha image url of error 14 "Captcha needed"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub captcha_img: Option<String>,
+    /// Url to pass validation of error 17 "Validation required"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect_uri: Option<String>,
+    /// Text of error 24 "Confirmation required"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confirmation_text: Option<String>,
+    /// Error description which may be shown to user
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_text: Option<String>,
+    /// Any other fields of the error
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl VkError {
+    /// See [`ErrorCode::is_rate_limit`]
+    #[must_use]
+    pub const fn is_rate_limit(&self) -> bool {
+        self.error_code.is_rate_limit()
+    }
+
+    /// See [`ErrorCode::is_retryable`]
+    #[must_use]
+    pub const fn is_retryable(&self) -> bool {
+        self.error_code.is_retryable()
+    }
+
+    /// See [`ErrorCode::is_auth`]
+    #[must_use]
+    pub const fn is_auth(&self) -> bool {
+        self.error_code.is_auth()
+    }
 }
 
 impl std::fmt::Display for VkError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self.request_params {
-            Some(params) => {
-                write!(
-                    f,
-                    "Error {}: {}\nRequest params: {:#?}",
-                    self.error_code, self.error_msg, params
-                )
-            }
-            None => {
-                write!(f, "Error {}: {}", self.error_code, self.error_msg)
-            }
+        write!(f, "Error {}", self.error_code)?;
+
+        if let Some(method) = &self.method {
+            write!(f, " in {method}")?;
         }
+
+        write!(f, ": {}", self.error_msg)?;
+
+        if let Some(params) = &self.request_params {
+            write!(f, "\nRequest params: {params:#?}")?;
+        }
+
+        Ok(())
     }
 }
 
+/// Params whose values are replaced with `***` in [`VkError::request_params`]
+const SECRET_PARAMS: &[&str] = &["access_token", "client_secret", "password", "captcha_key"];
+
 /// Serializes [`HashMap`] from sequence of objects with fields key and value
 ///
 /// For example, serializes this json
@@ -75,7 +128,8 @@ impl std::fmt::Display for VkError {
 ///     { "key": "test", 1}
 /// ]
 /// ```
-/// into `HashMap { "meaning_of_life": 42, "test": 1 }`
+/// into `HashMap { "meaning_of_life": 42, "test": 1 }`.
+/// Values of secret params like `access_token` are redacted.
 pub fn params_from_pairs<'de, D>(d: D) -> StdResult<Option<HashMap<String, String>>, D::Error>
 where
     D: Deserializer<'de>,
@@ -88,16 +142,109 @@ where
 
     let mut map = HashMap::with_capacity(s.len());
 
-    for Pair { key, value } in s {
+    for Pair { key, mut value } in s {
+        if SECRET_PARAMS.contains(&key.as_str()) {
+            value = String::from("***");
+        }
         map.insert(key, value);
     }
 
     Ok(Some(map))
 }
 
+/// Deserializes optional string which VK sometimes sends as a number
+fn string_or_number<'de, D>(d: D) -> StdResult<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrNumber {
+        String(String),
+        Number(u64),
+    }
+
+    Ok(
+        Option::<StringOrNumber>::deserialize(d)?.map(|value| match value {
+            StringOrNumber::String(string) => string,
+            StringOrNumber::Number(number) => number.to_string(),
+        }),
+    )
+}
+
 /// Represents any Request Param
 #[derive(Debug, Deserialize, Serialize)]
 struct Pair {
     key: String,
     value: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn keeps_all_fields() {
+        let error: VkError = serde_json::from_value(json!({
+            "error_code": 17,
+            "error_msg": "Validation required",
+            "request_params": [{ "key": "method", "value": "users.get" }],
+            "redirect_uri": "https://vk.com/validate",
+            "ban_info": { "member_name": "Pavel" }
+        }))
+        .unwrap();
+
+        assert_eq!(error.error_code, ErrorCode::ValidationRequired);
+        assert!(error.is_auth());
+        assert_eq!(error.redirect_uri.as_deref(), Some("https://vk.com/validate"));
+        assert_eq!(error.extra["ban_info"], json!({ "member_name": "Pavel" }));
+        assert_eq!(
+            error.request_params,
+            Some(HashMap::from([(String::from("method"), String::from("users.get"))]))
+        );
+    }
+
+    #[test]
+    fn redacts_secret_params() {
+        let error: VkError = serde_json::from_value(json!({
+            "error_code": 5,
+            "error_msg": "User authorization failed",
+            "request_params": [
+                { "key": "access_token", "value": "1234567890abcdef" },
+                { "key": "v", "value": "5.131" }
+            ]
+        }))
+        .unwrap();
+
+        let params = error.request_params.as_ref().unwrap();
+        assert_eq!(params["access_token"], "***");
+        assert_eq!(params["v"], "5.131");
+        assert!(!error.to_string().contains("1234567890abcdef"));
+    }
+
+    #[test]
+    fn unknown_code() {
+        let error: VkError = serde_json::from_value(json!({
+            "error_code": 6,
+            "error_msg": "Too many requests per second",
+            "method": "users.get"
+        }))
+        .unwrap();
+        assert!(error.is_rate_limit() && error.is_retryable());
+        assert_eq!(error.method.as_deref(), Some("users.get"));
+
+        let code: ErrorCode = serde_json::from_value(json!(1117)).unwrap();
+        assert!(matches!(code, ErrorCode::Unknown(1117)));
+        assert_eq!(serde_json::to_value(code).unwrap(), json!(1117));
+
+        // Listed codes are never held by `Unknown`, and equal it when it's built by hand
+        assert!(matches!(ErrorCode::from(14), ErrorCode::CaptchaNeeded));
+        assert_eq!(ErrorCode::Unknown(14), ErrorCode::CaptchaNeeded);
+        assert!(ErrorCode::Unknown(6).is_rate_limit());
+        assert_eq!(
+            std::collections::HashSet::from([ErrorCode::Unknown(14), ErrorCode::CaptchaNeeded]).len(),
+            1
+        );
+    }
+}
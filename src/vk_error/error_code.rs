@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+
+macro_rules! error_codes {
+    ($($(#[$meta:meta])* $name:ident = $code:literal,)*) => {
+        /// Code of [`VkError`](crate::VkError)
+        ///
+        /// Construct it with `From<u16>`, which never returns [`ErrorCode::Unknown`] of a listed code.
+        /// Codes are compared and hashed by numeric value anyway,
+        /// so `ErrorCode::Unknown(14)` equals [`ErrorCode::CaptchaNeeded`].
+        ///
+        /// See <https://dev.vk.com/reference/errors>
+        #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+        #[serde(from = "u16", into = "u16")]
+        pub enum ErrorCode {
+            $($(#[$meta])* $name,)*
+            /// Any code not listed above
+            Unknown(u16),
+        }
+
+        impl From<u16> for ErrorCode {
+            fn from(code: u16) -> Self {
+                Self::Unknown(code).normalized()
+            }
+        }
+
+        impl From<ErrorCode> for u16 {
+            fn from(code: ErrorCode) -> Self {
+                match code {
+                    $(ErrorCode::$name => $code,)*
+                    ErrorCode::Unknown(code) => code,
+                }
+            }
+        }
+
+        impl ErrorCode {
+            /// Turns [`ErrorCode::Unknown`] of a listed code into its variant
+            const fn normalized(self) -> Self {
+                match self {
+                    Self::Unknown(code) => match code {
+                        $($code => Self::$name,)*
+                        _ => self,
+                    },
+                    _ => self,
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    /// Unknown error occurred
+    UnknownError = 1,
+    /// Application is disabled
+    AppDisabled = 2,
+    /// Unknown method passed
+    UnknownMethod = 3,
+    /// Incorrect signature
+    InvalidSignature = 4,
+    /// User authorization failed
+    AuthorizationFailed = 5,
+    /// Too many requests per second
+    TooManyRequests = 6,
+    /// Permission to perform this action is denied
+    PermissionDenied = 7,
+    /// Invalid request
+    InvalidRequest = 8,
+    /// Flood control
+    FloodControl = 9,
+    /// Internal server error
+    InternalServerError = 10,
+    /// Application must be disabled or user authorized in test mode
+    TestMode = 11,
+    /// Captcha needed
+    CaptchaNeeded = 14,
+    /// Access denied
+    AccessDenied = 15,
+    /// HTTPS required
+    HttpsRequired = 16,
+    /// Validation required
+    ValidationRequired = 17,
+    /// User was deleted or banned
+    UserDeleted = 18,
+    /// Permission denied for non-standalone applications
+    StandaloneOnly = 20,
+    /// Permitted only for standalone and Open API applications
+    StandaloneOrOpenApiOnly = 21,
+    /// Method was disabled
+    MethodDisabled = 23,
+    /// Confirmation required
+    ConfirmationRequired = 24,
+    /// Group token is invalid
+    GroupAuthFailed = 27,
+    /// Application token is invalid
+    AppAuthFailed = 28,
+    /// Rate limit reached
+    RateLimitReached = 29,
+    /// Profile is private
+    PrivateProfile = 30,
+    /// One of the parameters specified was missing or invalid
+    InvalidParameter = 100,
+    /// Invalid application API ID
+    InvalidAppId = 101,
+    /// Invalid user id
+    InvalidUserId = 113,
+    /// Invalid timestamp
+    InvalidTimestamp = 150,
+    /// Access to album denied
+    AlbumAccessDenied = 200,
+    /// Access to group denied
+    GroupAccessDenied = 203,
+}
+
+impl PartialEq for ErrorCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.code() == other.code()
+    }
+}
+
+impl Eq for ErrorCode {}
+
+impl std::hash::Hash for ErrorCode {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.code().hash(state);
+    }
+}
+
+impl ErrorCode {
+    /// Numeric value of the code
+    #[must_use]
+    pub fn code(self) -> u16 {
+        self.into()
+    }
+
+    /// Request rate of the token is exceeded: errors 6, 9 and 29
+    #[must_use]
+    pub const fn is_rate_limit(self) -> bool {
+        matches!(
+            self.normalized(),
+            Self::TooManyRequests | Self::FloodControl | Self::RateLimitReached
+        )
+    }
+
+    /// The same request may succeed if it's sent later: errors 1, 6 and 10
+    #[must_use]
+    pub const fn is_retryable(self) -> bool {
+        matches!(
+            self.normalized(),
+            Self::UnknownError | Self::TooManyRequests | Self::InternalServerError
+        )
+    }
+
+    /// Token is invalid or must be confirmed: errors 5, 17, 27 and 28
+    #[must_use]
+    pub const fn is_auth(self) -> bool {
+        matches!(
+            self.normalized(),
+            Self::AuthorizationFailed
+                | Self::ValidationRequired
+                | Self::GroupAuthFailed
+                | Self::AppAuthFailed
+        )
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::mock::MockVk;
+use futures::future::join_all;
+use serde_json::json;
+use vk_executive::config::TokenKind;
+use vk_executive::{Client, Error, Method};
+use vk_method::Params;
+
+fn any_method() -> MockVk {
+    MockVk::new(|_, _| Ok(json!(1)))
+}
+
+#[tokio::test]
+async fn methods_go_to_tokens_allowed_to_call_them() {
+    let mock = any_method();
+    let mut configs = mock.configs(2);
+    configs[0].kind = Some(TokenKind::Group);
+    configs[1].kind = Some(TokenKind::User);
+
+    let client = Client::from_configs(configs.into_iter());
+
+    let friends = (0..30).map(|_| client.method(Method::new("friends.get", Params::new())));
+    for result in join_all(friends).await {
+        result.unwrap();
+    }
+
+    assert!(mock.sent().iter().all(|sent| sent.token == "token1"));
+
+    let users = (0..30).map(|_| client.method(Method::new("users.get", Params::new())));
+    for result in join_all(users).await {
+        result.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn fails_early_when_no_token_qualifies() {
+    let mock = any_method();
+    let mut configs = mock.configs(2);
+    for config in &mut configs {
+        config.kind = Some(TokenKind::Service);
+    }
+
+    let client = Client::from_configs(configs.into_iter());
+
+    let result = client.method(Method::new("messages.send", Params::new())).await;
+
+    assert!(matches!(result, Err(Error::NoSuitableWorker(_))));
+    assert!(mock.sent().is_empty());
+}
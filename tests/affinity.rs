@@ -0,0 +1,40 @@
+mod common;
+
+use common::mock::{users_get, MockVk};
+use futures::future::join_all;
+use vk_executive::client::Options;
+use vk_executive::{Client, Error};
+
+#[tokio::test]
+async fn tagged_methods_run_on_tagged_tokens() {
+    let mock = MockVk::users();
+    let mut configs = mock.configs(3);
+    configs[1].tags.push(String::from("group"));
+
+    let client = Client::from_configs(configs.into_iter());
+
+    let tagged = (1..=50).map(|id| client.method_with(users_get(id), Options::new().tag("group")));
+    for result in join_all(tagged).await {
+        result.unwrap();
+    }
+
+    assert!(mock.sent().iter().all(|sent| sent.token == "token1"));
+
+    let untagged = (1..=50).map(|id| client.method(users_get(id)));
+    for result in join_all(untagged).await {
+        result.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn unknown_tag_fails_early() {
+    let mock = MockVk::users();
+    let client = Client::from_configs(mock.configs(2).into_iter());
+
+    let result = client
+        .method_with(users_get(1), Options::new().tag("group"))
+        .await;
+
+    assert!(matches!(result, Err(Error::NoSuitableWorker(_))));
+    assert!(mock.sent().is_empty());
+}
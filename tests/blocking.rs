@@ -0,0 +1,42 @@
+#![cfg(feature = "blocking")]
+
+mod common;
+
+use common::mock::{users_get, MockVk};
+use serde::Deserialize;
+use serde_json::json;
+use vk_executive::{blocking, Error};
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct User {
+    id: u64,
+}
+
+#[test]
+fn calls_methods_synchronously() {
+    let mock = MockVk::users();
+    let client = blocking::Client::from_configs(mock.configs(2).into_iter()).unwrap();
+
+    assert_eq!(client.method(users_get(1)).unwrap(), json!([{ "id": 1 }]));
+    assert_eq!(
+        client.method_as::<Vec<User>>(users_get(2)).unwrap(),
+        [User { id: 2 }]
+    );
+    assert!(matches!(
+        client.method_as::<String>(users_get(3)),
+        Err(Error::Serialization(_))
+    ));
+}
+
+#[test]
+fn batch_keeps_order_and_shares_executes() {
+    let mock = MockVk::users();
+    let client = blocking::Client::from_configs(mock.configs(1).into_iter()).unwrap();
+
+    let results = client.methods((1..=50).map(users_get));
+
+    for (user_id, result) in (1..=50).zip(results) {
+        assert_eq!(result.unwrap(), json!([{ "id": user_id }]));
+    }
+    assert!(mock.sent().len() < 50);
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use common::mock::{users_get, MockVk};
+use futures::{stream, StreamExt};
+use serde_json::json;
+use vk_executive::client::{Bulk, Progress};
+use vk_executive::{config, Client, Config, Method};
+use vk_method::Params;
+
+use std::pin::pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[tokio::test]
+async fn ordered_results_follow_methods() {
+    let mock = MockVk::users();
+    let client: Client<MockVk> = Client::from_configs(mock.configs(3).into_iter());
+
+    let methods = stream::iter(1..=200).map(users_get);
+    let results: Vec<_> = client
+        .methods_with(methods, Bulk::new().ordered(true))
+        .collect()
+        .await;
+
+    assert_eq!(results.len(), 200);
+    for (expected, (index, result)) in results.into_iter().enumerate() {
+        assert_eq!(index, expected);
+        assert_eq!(result.unwrap(), json!([{ "id": index + 1 }]));
+    }
+}
+
+#[tokio::test]
+async fn in_flight_is_bounded() {
+    let mock = MockVk::users();
+    let client: Client<MockVk> = Client::from_configs(mock.configs(2).into_iter());
+
+    let pulled = Arc::new(AtomicUsize::new(0));
+    let max_in_flight = Arc::new(AtomicUsize::new(0));
+
+    let methods = stream::iter(1..=300).map({
+        let pulled = pulled.clone();
+        move |user_id| {
+            pulled.fetch_add(1, Ordering::SeqCst);
+            users_get(user_id)
+        }
+    });
+
+    let bulk = Bulk::new().in_flight(10).on_progress({
+        let pulled = pulled.clone();
+        let max_in_flight = max_in_flight.clone();
+        move |progress| {
+            let in_flight = pulled.load(Ordering::SeqCst) - progress.completed + 1;
+            max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        }
+    });
+
+    let mut results = client.methods_with(methods, bulk);
+    let mut indexes = Vec::new();
+    while let Some((index, result)) = results.next().await {
+        assert_eq!(result.unwrap(), json!([{ "id": index + 1 }]));
+        indexes.push(index);
+    }
+
+    indexes.sort_unstable();
+    assert_eq!(indexes, (0..300).collect::<Vec<_>>());
+    assert!(max_in_flight.load(Ordering::SeqCst) <= 10);
+}
+
+#[tokio::test]
+async fn progress_counts_failures() {
+    let mock = MockVk::users();
+    let client: Client<MockVk> = Client::from_configs(mock.configs(1).into_iter());
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let methods = stream::iter([
+        users_get(1),
+        Method::new("unknown.method", Params::new()),
+        users_get(2),
+    ]);
+
+    let bulk = Bulk::new().on_progress({
+        let reports = reports.clone();
+        move |progress| reports.lock().unwrap().push(progress)
+    });
+
+    let results: Vec<_> = client.methods_with(methods, bulk).collect().await;
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(
+        reports.lock().unwrap().last(),
+        Some(&Progress {
+            completed: 3,
+            failed: 1
+        })
+    );
+}
+
+#[tokio::test]
+async fn dropped_stream_leaves_other_callers_answered() {
+    let mock = MockVk::users();
+    // Leaves time to drop the stream before the second execute is sent
+    let prototype = config::Builder::with_http_client(mock.clone())
+        .time_between_requests(Duration::from_millis(50));
+    let configs = Config::from_tokens_by_prototype(["token0"].into_iter(), &prototype).unwrap();
+    let client = Client::from_configs(configs.into_iter());
+
+    // 30 methods of the stream are queued before the lone one,
+    // so the second execute carries 5 of them and then the lone method
+    let methods = stream::iter(1..=100).map(users_get);
+    let mut results = client.methods_with(methods, Bulk::new().in_flight(30));
+    let mut lone = pin!(client.method(users_get(1000)));
+
+    assert!(futures::poll!(results.next()).is_pending());
+    assert!(futures::poll!(lone.as_mut()).is_pending());
+
+    results.next().await.unwrap().1.unwrap();
+    drop(results);
+
+    assert_eq!(lone.await.unwrap(), json!([{ "id": 1000 }]));
+    assert_eq!(mock.sent().len(), 2);
+}
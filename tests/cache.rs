@@ -0,0 +1,75 @@
+mod common;
+
+use common::mock::{param, users_get, MockVk};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use vk_executive::client::{self, Middleware};
+use vk_executive::{Client, Method};
+use vk_method::Params;
+
+/// Sets `lang` which may change between calls
+#[derive(Clone)]
+struct Lang(Arc<Mutex<&'static str>>);
+
+impl Middleware for Lang {
+    fn before(&self, method: &mut Method) {
+        method.params.insert("lang", *self.0.lock().unwrap());
+    }
+}
+
+#[tokio::test]
+async fn cache_hits_skip_workers() {
+    let mock = MockVk::users();
+
+    let client: Client<MockVk> = client::Builder::new()
+        .cache_method("users.get", Duration::from_secs(60))
+        .build(mock.configs(1).into_iter());
+
+    let first = client.method(users_get(1)).await.unwrap();
+    let second = client.method(users_get(1)).await.unwrap();
+    client.method(users_get(2)).await.unwrap();
+
+    assert_eq!(first, second);
+    assert_eq!(mock.sent().len(), 2);
+    assert_eq!(client.stats().workers[0].methods, 2);
+}
+
+#[tokio::test]
+async fn errors_and_other_methods_are_not_cached() {
+    let mock = MockVk::users();
+
+    let client: Client<MockVk> = client::Builder::new()
+        .cache_method("wall.get", Duration::from_secs(60))
+        .build(mock.configs(1).into_iter());
+
+    for _ in 0..2 {
+        client.method(users_get(1)).await.unwrap();
+        client.method(Method::new("wall.get", Params::new())).await.unwrap_err();
+    }
+
+    assert_eq!(mock.sent().len(), 4);
+}
+
+#[tokio::test]
+async fn params_set_by_middleware_are_part_of_keys() {
+    let mock = MockVk::users();
+    let lang = Lang(Arc::new(Mutex::new("en")));
+
+    let client: Client<MockVk> = client::Builder::new()
+        .middleware(lang.clone())
+        .cache_method("users.get", Duration::from_secs(60))
+        .build(mock.configs(1).into_iter());
+
+    client.method(users_get(1)).await.unwrap();
+    *lang.0.lock().unwrap() = "ru";
+    client.method(users_get(1)).await.unwrap();
+    client.method(users_get(1)).await.unwrap();
+
+    let langs: Vec<_> = mock
+        .sent()
+        .iter()
+        .flat_map(|sent| &sent.methods)
+        .map(|(_, params)| param(params, "lang").unwrap())
+        .collect();
+    assert_eq!(langs, ["en", "ru"]);
+}
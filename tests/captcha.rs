@@ -0,0 +1,79 @@
+mod common;
+
+use common::mock::{param, MockVk};
+use serde_json::json;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use vk_executive::client::{self, Captcha, CaptchaSolver, Solution};
+use vk_executive::{Client, Error, Method};
+use vk_method::Params;
+
+/// Requires captcha until `captcha_key=secret` is passed
+fn protected() -> MockVk {
+    MockVk::new(|_, params| match param(params, "captcha_key").as_deref() {
+        Some("secret") => {
+            assert_eq!(param(params, "captcha_sid").as_deref(), Some("42"));
+            Ok(json!(1))
+        }
+        _ => Err((14, String::from("Captcha needed"))),
+    })
+}
+
+#[derive(Clone, Default)]
+struct Solver {
+    key: Option<&'static str>,
+    calls: Arc<AtomicUsize>,
+}
+
+impl CaptchaSolver for Solver {
+    fn solve(&self, captcha: Captcha) -> Solution<'_> {
+        assert_eq!(captcha.method, "wall.post");
+        assert_eq!(captcha.sid, "42");
+        self.calls.fetch_add(1, Ordering::Relaxed);
+
+        Box::pin(async move { self.key.map(String::from) })
+    }
+}
+
+#[tokio::test]
+async fn method_is_resent_with_solved_captcha() {
+    let mock = protected();
+    let solver = Solver {
+        key: Some("secret"),
+        ..Solver::default()
+    };
+
+    let client: Client<MockVk> = client::Builder::new()
+        .captcha_solver(solver.clone())
+        .build(mock.configs(1).into_iter());
+
+    let result = client.method(Method::new("wall.post", Params::new())).await;
+
+    assert_eq!(result.unwrap(), json!(1));
+    assert_eq!(solver.calls.load(Ordering::Relaxed), 1);
+    assert_eq!(mock.sent().len(), 2);
+}
+
+#[tokio::test]
+async fn gives_up_after_attempts() {
+    let mock = protected();
+    let solver = Solver {
+        key: Some("wrong"),
+        ..Solver::default()
+    };
+
+    let client: Client<MockVk> = client::Builder::new()
+        .captcha_solver(solver.clone())
+        .build(mock.configs(1).into_iter());
+
+    let result = client.method(Method::new("wall.post", Params::new())).await;
+
+    match result {
+        Err(Error::VK(error)) => assert_eq!(error.captcha_sid.as_deref(), Some("42")),
+        result => panic!("unexpected result {result:?}"),
+    }
+    assert_eq!(
+        solver.calls.load(Ordering::Relaxed),
+        usize::from(client::MAX_CAPTCHA_ATTEMPTS)
+    );
+}
@@ -0,0 +1,92 @@
+#![cfg(feature = "cli")]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::process::Command;
+use std::thread;
+
+const BODY: &str = r#"{"response":[{"id":1}]}"#;
+
+/// Stand-in API answering one request, returns its head and body
+fn serve_once(listener: TcpListener) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+
+        let mut head = String::new();
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                length = value.trim().parse().unwrap();
+            }
+            head.push_str(&line);
+            if line == "\r\n" {
+                break;
+            }
+        }
+
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{BODY}",
+            BODY.len()
+        );
+        reader.get_mut().write_all(response.as_bytes()).unwrap();
+
+        head + &String::from_utf8(body).unwrap()
+    })
+}
+
+fn vk_executive() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_vk-executive"));
+    command.env_remove("VK_TOKENS");
+    command
+}
+
+#[test]
+fn runs_method_and_prints_json() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let request = serve_once(listener);
+
+    let output = vk_executive()
+        .env("VK_TOKENS", "token")
+        .args(["--api-url", &format!("http://{address}/")])
+        .args(["--api-version", "5.199", "--compact"])
+        .args(["users.get", "user_ids=1", "fields=city"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "[{\"id\":1}]\n");
+
+    let request = request.join().unwrap();
+    assert!(request.starts_with("POST /method/users.get"), "{request}");
+    assert!(request.contains("v=5.199"), "{request}");
+    assert!(request.contains("user_ids=1"), "{request}");
+    assert!(request.contains("fields=city"), "{request}");
+}
+
+#[test]
+fn rejects_malformed_params() {
+    let output = vk_executive()
+        .env("VK_TOKENS", "token")
+        .args(["users.get", "user_ids"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("expected `key=value`"));
+}
+
+#[test]
+fn fails_without_tokens() {
+    let output = vk_executive().arg("users.get").output().unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("TOKENS"));
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use common::mock::{users_get, MockVk};
+use futures::future::join_all;
+use serde_json::json;
+use vk_executive::client::{Options, Priority};
+use vk_executive::{client, Client};
+
+#[tokio::test]
+async fn identical_methods_are_sent_once() {
+    let mock = MockVk::users();
+
+    let client: Client<MockVk> = client::Builder::new()
+        .coalesce(true)
+        .build(mock.configs(1).into_iter());
+
+    let methods = (0..20).map(|i| users_get(if i % 2 == 0 { 1 } else { 2 }));
+    let results = join_all(methods.map(|method| client.method(method))).await;
+
+    for (i, result) in results.into_iter().enumerate() {
+        let id = if i % 2 == 0 { 1 } else { 2 };
+        assert_eq!(result.unwrap(), json!([{ "id": id }]));
+    }
+
+    assert_eq!(client.stats().workers[0].methods, 2);
+
+    client.method(users_get(1)).await.unwrap();
+    assert_eq!(client.stats().workers[0].methods, 3);
+}
+
+#[tokio::test]
+async fn methods_of_different_priorities_are_not_joined() {
+    let mock = MockVk::users();
+
+    let client: Client<MockVk> = client::Builder::new()
+        .coalesce(true)
+        .build(mock.configs(1).into_iter());
+
+    let low = client.method_with(users_get(1), Options::new().priority(Priority::Low));
+    let high = client.method_with(users_get(1), Options::new().priority(Priority::High));
+    let (low, high) = futures::join!(low, high);
+
+    assert_eq!(low.unwrap(), high.unwrap());
+    assert_eq!(client.stats().workers[0].methods, 2);
+}
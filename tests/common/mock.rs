@@ -0,0 +1,194 @@
+//! Fake VK API implemented as [`tower::Service`]
+//!
+//! It understands both standalone methods and `execute` code compiled by `vk_execute_compiler`.
+
+use http::{Request, Response};
+use hyper::Body;
+use serde_json::{json, Map, Value};
+use std::future::{ready, Ready};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tower::Service;
+use vk_executive::{config, Config, Method};
+use vk_method::{PairsArray, Params};
+
+/// Computes response of single method from its name and params
+pub type Handler = dyn Fn(&str, &Map<String, Value>) -> Result<Value, (u16, String)> + Send + Sync;
+
+/// Http request received by [`MockVk`]
+#[derive(Debug, Clone)]
+pub struct Sent {
+    pub token: String,
+    pub query: Map<String, Value>,
+    /// Methods carried by the request. Single one for standalone method
+    pub methods: Vec<(String, Map<String, Value>)>,
+}
+
+#[derive(Clone)]
+pub struct MockVk {
+    handler: Arc<Handler>,
+    sent: Arc<Mutex<Vec<Sent>>>,
+}
+
+impl MockVk {
+    pub fn new<H>(handler: H) -> Self
+    where
+        H: Fn(&str, &Map<String, Value>) -> Result<Value, (u16, String)> + Send + Sync + 'static,
+    {
+        Self {
+            handler: Arc::new(handler),
+            sent: Arc::default(),
+        }
+    }
+
+    /// Answers `users.get` with `[{"id": user_id}]` and fails any other method
+    pub fn users() -> Self {
+        Self::new(|name, params| match name {
+            "users.get" => {
+                let ids = param(params, "user_ids").or_else(|| param(params, "user_id"));
+                let ids = ids.unwrap_or_default();
+
+                Ok(ids
+                    .split(',')
+                    .map(|id| json!({ "id": id.parse::<u64>().unwrap() }))
+                    .collect())
+            }
+            _ => Err((3, String::from("Unknown method passed"))),
+        })
+    }
+
+    /// Requests received so far
+    pub fn sent(&self) -> Vec<Sent> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    /// Builds `count` configs sharing this mock
+    pub fn configs(&self, count: usize) -> Vec<Config<Self>> {
+        let prototype = config::Builder::with_http_client(self.clone())
+            .time_between_requests(Duration::from_millis(1));
+
+        Config::from_tokens_by_prototype((0..count).map(|i| format!("token{i}")), &prototype)
+            .unwrap()
+    }
+
+    fn respond(&self, request: &Request<Body>) -> Value {
+        let query: Map<String, Value> = request
+            .uri()
+            .query()
+            .map(|query| {
+                url::form_urlencoded::parse(query.as_bytes())
+                    .map(|(k, v)| (k.into_owned(), Value::String(v.into_owned())))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let name = request.uri().path().rsplit('/').next().unwrap().to_string();
+
+        let methods = if name == "execute" {
+            parse_execute(query["code"].as_str().unwrap())
+        } else {
+            let mut params = query.clone();
+            params.remove("access_token");
+            params.remove("v");
+            vec![(name.clone(), params)]
+        };
+
+        self.sent.lock().unwrap().push(Sent {
+            token: param(&query, "access_token").unwrap(),
+            query: query.clone(),
+            methods: methods.clone(),
+        });
+
+        if name != "execute" {
+            let (name, params) = &methods[0];
+            return match (self.handler)(name, params) {
+                Ok(response) => json!({ "response": response }),
+                Err((code, message)) => json!({ "error": error(code, &message, None) }),
+            };
+        }
+
+        let mut responses = Vec::new();
+        let mut errors = Vec::new();
+
+        for (name, params) in &methods {
+            match (self.handler)(name, params) {
+                Ok(response) => responses.push(response),
+                Err((code, message)) => {
+                    responses.push(Value::Bool(false));
+                    errors.push(error(code, &message, Some(name)));
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            json!({ "response": responses })
+        } else {
+            json!({ "response": responses, "execute_errors": errors })
+        }
+    }
+}
+
+impl Service<Request<Body>> for MockVk {
+    type Response = Response<Body>;
+    type Error = hyper::Error;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let body = self.respond(&request).to_string();
+        ready(Ok(Response::new(Body::from(body))))
+    }
+}
+
+/// Error object. Error 14 carries captcha with sid `42`
+fn error(code: u16, message: &str, method: Option<&str>) -> Value {
+    let mut error = json!({ "error_code": code, "error_msg": message, "request_params": [] });
+
+    if let Some(method) = method {
+        error["method"] = json!(method);
+    }
+
+    if code == 14 {
+        error["captcha_sid"] = json!("42");
+        error["captcha_img"] = json!("https://api.vk.com/captcha.php?sid=42");
+    }
+
+    error
+}
+
+/// `users.get` of a single user, answered by [`MockVk::users`]
+pub fn users_get(user_id: u64) -> Method {
+    Method::new(
+        "users.get",
+        Params::try_from(PairsArray([("user_id", user_id)])).unwrap(),
+    )
+}
+
+/// Returns param as string regardless of its json type
+pub fn param(params: &Map<String, Value>, key: &str) -> Option<String> {
+    params.get(key).map(|value| match value {
+        Value::String(string) => string.clone(),
+        value => value.to_string(),
+    })
+}
+
+/// Parses `var resultN = API.name({...});` statements
+fn parse_execute(code: &str) -> Vec<(String, Map<String, Value>)> {
+    code.split("var result")
+        .skip(1)
+        .map(|statement| {
+            let call = &statement[statement.find("API.").unwrap() + 4..];
+            let open = call.find('(').unwrap();
+            let close = call.rfind(");").unwrap();
+
+            (
+                call[..open].to_string(),
+                serde_json::from_str(&call[open + 1..close]).unwrap(),
+            )
+        })
+        .collect()
+}
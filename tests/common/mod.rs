@@ -1,3 +1,7 @@
+#![allow(dead_code)]
+
+pub mod mock;
+
 use once_cell::sync::Lazy;
 use serde_json::Value;
 
@@ -0,0 +1,25 @@
+mod common;
+
+use common::mock::{users_get, MockVk};
+use futures::future::join_all;
+use vk_executive::config::BuildError;
+use vk_executive::{Client, Error};
+
+#[tokio::test]
+async fn invalid_api_url_of_config_constructed_by_hand_is_an_error() {
+    let mock = MockVk::users();
+    let mut configs = mock.configs(1);
+    configs[0].api_url = String::from("not a url");
+    let client = Client::from_configs(configs.into_iter());
+
+    let single = client.method(users_get(1)).await;
+    let execute = join_all((2..=5).map(|user_id| client.method(users_get(user_id)))).await;
+
+    for result in execute.into_iter().chain([single]) {
+        assert!(matches!(
+            result,
+            Err(Error::InvalidConfig(BuildError::InvalidApiUrl(..)))
+        ));
+    }
+    assert!(mock.sent().is_empty());
+}
@@ -0,0 +1,187 @@
+mod common;
+
+use common::mock::{users_get, MockVk};
+use futures::stream;
+use futures::StreamExt;
+use serde_json::json;
+use vk_executive::client::{journal::Journal, Bulk};
+use vk_executive::{config, Client, Config, Method};
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::pin::pin;
+use std::time::Duration;
+
+fn journal_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "vk_executive-{}-{name}.journal",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[tokio::test]
+async fn finished_crawl_leaves_nothing_to_replay() {
+    let path = journal_path("finished");
+    let mock = MockVk::users();
+    let client: Client<MockVk> = Client::from_configs(mock.configs(2).into_iter());
+
+    let journal = Journal::open(&path).unwrap();
+    let mut results = BTreeMap::new();
+    let mut sink = |id, _, result: vk_executive::Result<_>| {
+        results.insert(id, result.unwrap());
+        Ok(())
+    };
+
+    let methods = stream::iter(1..=100).map(users_get);
+    journal
+        .run(&client, methods, Bulk::new(), &mut sink)
+        .await
+        .unwrap();
+
+    assert_eq!(results.len(), 100);
+    assert_eq!(results[&0], json!([{ "id": 1 }]));
+
+    let journal = Journal::open(&path).unwrap();
+    assert_eq!(journal.recovered(), 0);
+    assert_eq!(journal.submitted(), 100);
+}
+
+#[tokio::test]
+async fn unfinished_methods_are_replayed() {
+    let path = journal_path("replayed");
+    let mock = MockVk::users();
+    let client: Client<MockVk> = Client::from_configs(mock.configs(1).into_iter());
+
+    let mut delivered = BTreeMap::new();
+    {
+        let journal = Journal::open(&path).unwrap();
+        // The sink dies after 30 results
+        let mut sink = |id, method: Method, result: vk_executive::Result<_>| {
+            if delivered.len() == 30 {
+                return Err(io::Error::other("sink is down"));
+            }
+            assert_eq!(method.name, "users.get");
+            delivered.insert(id, result.unwrap());
+            Ok(())
+        };
+
+        let methods = stream::iter(1..=100).map(users_get);
+        let bulk = Bulk::new().in_flight(10);
+        assert!(journal
+            .run(&client, methods, bulk, &mut sink)
+            .await
+            .is_err());
+    }
+
+    let journal = Journal::open(&path).unwrap();
+    assert!(journal.recovered() >= 1);
+    let submitted = journal.submitted();
+    assert!(submitted < 100);
+
+    let mut sink = |id, _, result: vk_executive::Result<_>| {
+        delivered.insert(id, result.unwrap());
+        Ok(())
+    };
+    let rest = stream::iter(submitted + 1..=100).map(users_get);
+    journal
+        .run(&client, rest, Bulk::new(), &mut sink)
+        .await
+        .unwrap();
+
+    assert_eq!(delivered.len(), 100);
+    for (id, value) in delivered {
+        assert_eq!(value, json!([{ "id": id + 1 }]));
+    }
+}
+
+#[tokio::test]
+async fn failed_sink_leaves_client_serving() {
+    let path = journal_path("failed-sink");
+    let mock = MockVk::users();
+    // Leaves methods of the second execute in flight when the sink fails
+    let prototype = config::Builder::with_http_client(mock.clone())
+        .time_between_requests(Duration::from_millis(50));
+    let configs = Config::from_tokens_by_prototype(["token0"].into_iter(), &prototype).unwrap();
+    let client = Client::from_configs(configs.into_iter());
+
+    {
+        let journal = Journal::open(&path).unwrap();
+        let mut sink = |_, _, _| Err(io::Error::other("sink is down"));
+
+        // The second execute carries 5 journaled methods and then the lone one
+        let methods = stream::iter(1..=100).map(users_get);
+        let mut run = pin!(journal.run(&client, methods, Bulk::new().in_flight(30), &mut sink));
+        let mut lone = pin!(client.method(users_get(1000)));
+
+        assert!(futures::poll!(run.as_mut()).is_pending());
+        assert!(futures::poll!(lone.as_mut()).is_pending());
+
+        assert!(run.await.is_err());
+        assert_eq!(lone.await.unwrap(), json!([{ "id": 1000 }]));
+    }
+
+    let value = client.method(users_get(2000)).await.unwrap();
+    assert_eq!(value, json!([{ "id": 2000 }]));
+
+    let journal = Journal::open(&path).unwrap();
+    assert_eq!(journal.recovered(), 30);
+    assert_eq!(journal.submitted(), 30);
+
+    let mut replayed = Vec::new();
+    let mut sink = |id, _, result: vk_executive::Result<_>| {
+        assert_eq!(result.unwrap(), json!([{ "id": id + 1 }]));
+        replayed.push(id);
+        Ok(())
+    };
+    journal
+        .run(
+            &client,
+            stream::empty(),
+            Bulk::new().ordered(true),
+            &mut sink,
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(replayed, (0..30).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn torn_last_line_is_ignored() {
+    let path = journal_path("torn");
+    let journal = Journal::open(&path).unwrap();
+    journal.submit(&users_get(1)).unwrap();
+    journal.submit(&users_get(2)).unwrap();
+    journal.done(0).unwrap();
+    drop(journal);
+
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    file.write_all(b"{\"id\":2,\"name\":\"users.g").unwrap();
+    drop(file);
+
+    let journal = Journal::open(&path).unwrap();
+    assert_eq!(journal.recovered(), 1);
+    assert_eq!(journal.submitted(), 2);
+}
+
+#[test]
+fn compaction_keeps_files_differing_in_extension() {
+    let path = journal_path("extension");
+    let neighbour = path.with_extension("compacting");
+    std::fs::write(&neighbour, "neighbour").unwrap();
+
+    let journal = Journal::open(&path).unwrap();
+    journal.submit(&users_get(1)).unwrap();
+    drop(journal);
+
+    let journal = Journal::open(&path).unwrap();
+    assert_eq!(journal.recovered(), 1);
+    assert_eq!(std::fs::read_to_string(&neighbour).unwrap(), "neighbour");
+    std::fs::remove_file(neighbour).unwrap();
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use common::mock::MockVk;
+use futures::future::join_all;
+use vk_executive::{Client, Config, Method};
+use vk_method::{PairsArray, Params};
+
+use std::time::Duration;
+use tokio::time::sleep;
+
+fn client(mock: &MockVk, linger: Duration, min_batch_size: usize) -> Client<MockVk> {
+    let configs: Vec<Config<MockVk>> = mock
+        .configs(1)
+        .into_iter()
+        .map(|mut config| {
+            config.linger = linger;
+            config.min_batch_size = min_batch_size;
+            config
+        })
+        .collect();
+
+    Client::from_configs(configs.into_iter())
+}
+
+/// Sends methods one by one with 5ms gaps
+async fn trickle(client: &Client<MockVk>, count: u64) {
+    let calls = (0..count).map(|user_id| async move {
+        sleep(Duration::from_millis(5 * user_id)).await;
+
+        let params = Params::try_from(PairsArray([("user_id", user_id)])).unwrap();
+        client.method(Method::new("users.get", params)).await
+    });
+
+    let results = join_all(calls).await;
+    assert!(results.iter().all(Result::is_ok));
+}
+
+#[tokio::test]
+async fn linger_gathers_trickling_methods() {
+    let mock = MockVk::users();
+    let client = client(&mock, Duration::from_millis(500), 5);
+
+    trickle(&client, 5).await;
+
+    let sent = mock.sent();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].methods.len(), 5);
+}
+
+#[tokio::test]
+async fn linger_ends_by_timeout() {
+    let mock = MockVk::users();
+    let client = client(&mock, Duration::from_millis(12), 25);
+
+    trickle(&client, 5).await;
+
+    let sent = mock.sent();
+    assert!(sent.len() > 1);
+    assert_eq!(sent.iter().map(|sent| sent.methods.len()).sum::<usize>(), 5);
+}
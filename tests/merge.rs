@@ -0,0 +1,190 @@
+mod common;
+
+use common::mock::{param, MockVk};
+use futures::future::join_all;
+use serde_json::json;
+use vk_executive::client::{self, merge::Strategy};
+use vk_executive::{Client, Error, ErrorCode, Method};
+use vk_method::{PairsArray, Params};
+
+#[tokio::test]
+async fn single_id_methods_are_merged_and_split() {
+    let mock = MockVk::users();
+
+    let client: Client<MockVk> = client::Builder::new()
+        .merge(Strategy::users_get())
+        .build(mock.configs(1).into_iter());
+
+    let methods = (1..=100).map(|user_id| {
+        Method::new(
+            "users.get",
+            Params::try_from(PairsArray([("user_id", user_id)])).unwrap(),
+        )
+    });
+    let results = join_all(methods.map(|method| client.method(method))).await;
+
+    for (user_id, result) in (1..=100).zip(results) {
+        assert_eq!(result.unwrap(), json!([{ "id": user_id }]));
+    }
+
+    let sent = mock.sent();
+    let methods: Vec<_> = sent.iter().flat_map(|sent| &sent.methods).collect();
+
+    assert!(methods.len() < 100);
+    assert!(methods
+        .iter()
+        .any(|(_, params)| param(params, "user_ids").is_some_and(|ids| ids.contains(','))));
+}
+
+#[tokio::test]
+async fn methods_with_different_params_are_not_merged() {
+    let mock = MockVk::users();
+
+    let client: Client<MockVk> = client::Builder::new()
+        .merge(Strategy::users_get())
+        .build(mock.configs(1).into_iter());
+
+    let first = Method::new(
+        "users.get",
+        Params::try_from(PairsArray([("user_id", "1"), ("fields", "sex")])).unwrap(),
+    );
+    let second = Method::new(
+        "users.get",
+        Params::try_from(PairsArray([("user_id", "2"), ("fields", "city")])).unwrap(),
+    );
+
+    let (first, second) = tokio::join!(client.method(first), client.method(second));
+
+    assert_eq!(first.unwrap(), json!([{ "id": 1 }]));
+    assert_eq!(second.unwrap(), json!([{ "id": 2 }]));
+    assert!(mock
+        .sent()
+        .iter()
+        .flat_map(|sent| &sent.methods)
+        .all(|(_, params)| param(params, "user_ids").is_none()));
+}
+
+/// Answers `groups.getById` with `{"groups": [{"id": group_id}], "profiles": []}`
+fn groups() -> MockVk {
+    MockVk::new(|name, params| match name {
+        "groups.getById" => {
+            let ids = param(params, "group_ids").or_else(|| param(params, "group_id"));
+            let groups: Vec<_> = ids
+                .unwrap_or_default()
+                .split(',')
+                .map(|id| json!({ "id": id.parse::<u64>().unwrap() }))
+                .collect();
+
+            Ok(json!({ "groups": groups, "profiles": [] }))
+        }
+        _ => Err((3, String::from("Unknown method passed"))),
+    })
+}
+
+fn groups_get_by_id(group_id: u64) -> Method {
+    Method::new(
+        "groups.getById",
+        Params::try_from(PairsArray([("group_id", group_id)])).unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn object_responses_are_split_by_items_field() {
+    let mock = groups();
+
+    let client: Client<MockVk> = client::Builder::new()
+        .merge(Strategy::groups_get_by_id())
+        .build(mock.configs(1).into_iter());
+
+    let results =
+        join_all((1..=10).map(|group_id| client.method(groups_get_by_id(group_id)))).await;
+
+    for (group_id, result) in (1..=10).zip(results) {
+        assert_eq!(
+            result.unwrap(),
+            json!({ "groups": [{ "id": group_id }], "profiles": [] })
+        );
+    }
+
+    let sent = mock.sent();
+    assert!(sent.iter().flat_map(|sent| &sent.methods).count() < 10);
+}
+
+#[tokio::test]
+async fn responses_of_unknown_shape_are_not_split() {
+    let mock = groups();
+
+    let strategy = Strategy {
+        items_field: None,
+        ..Strategy::groups_get_by_id()
+    };
+    let client: Client<MockVk> = client::Builder::new()
+        .merge(strategy)
+        .build(mock.configs(1).into_iter());
+
+    let results =
+        join_all((1..=10).map(|group_id| client.method(groups_get_by_id(group_id)))).await;
+
+    // Merged callers must not get responses of each other
+    let mut failed = 0;
+    for result in results {
+        match result {
+            Ok(response) => assert_eq!(response["groups"].as_array().unwrap().len(), 1),
+            Err(error) => {
+                assert!(matches!(error, Error::Serialization(_)));
+                failed += 1;
+            }
+        }
+    }
+    assert!(failed > 0);
+}
+
+/// Answers `users.get` only for even ids, like VK skipping unknown ids of a list
+fn even_users() -> MockVk {
+    MockVk::new(|name, params| match name {
+        "users.get" => {
+            let ids = param(params, "user_ids").or_else(|| param(params, "user_id"));
+            let users: Vec<_> = ids
+                .unwrap_or_default()
+                .split(',')
+                .map(|id| id.parse::<u64>().unwrap())
+                .filter(|id| id % 2 == 0)
+                .map(|id| json!({ "id": id }))
+                .collect();
+
+            if users.is_empty() {
+                return Err((113, String::from("Invalid user id")));
+            }
+            Ok(json!(users))
+        }
+        _ => Err((3, String::from("Unknown method passed"))),
+    })
+}
+
+#[tokio::test]
+async fn merged_callers_of_unknown_ids_get_errors() {
+    let mock = even_users();
+
+    let client: Client<MockVk> = client::Builder::new()
+        .merge(Strategy::users_get())
+        .build(mock.configs(1).into_iter());
+
+    let methods = (1..=10).map(|user_id| {
+        Method::new(
+            "users.get",
+            Params::try_from(PairsArray([("user_id", user_id)])).unwrap(),
+        )
+    });
+    let results = join_all(methods.map(|method| client.method(method))).await;
+
+    for (user_id, result) in (1..=10).zip(results) {
+        match result {
+            Ok(response) if user_id % 2 == 0 => assert_eq!(response, json!([{ "id": user_id }])),
+            Err(Error::VK(error)) if user_id % 2 == 1 => {
+                assert_eq!(error.error_code, ErrorCode::InvalidUserId);
+            }
+            result => panic!("unexpected result of {user_id}: {result:?}"),
+        }
+    }
+    assert!(mock.sent().iter().flat_map(|sent| &sent.methods).count() < 10);
+}
@@ -0,0 +1,83 @@
+mod common;
+
+use common::mock::{param, users_get, MockVk};
+use futures::future::join_all;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use vk_executive::client::{self, merge::Strategy, Middleware};
+use vk_executive::{Client, Method, Result};
+use vk_method::{PairsArray, Params};
+
+struct Lang;
+
+impl Middleware for Lang {
+    fn before(&self, method: &mut Method) {
+        method.params.insert("lang", "en");
+    }
+}
+
+#[derive(Clone, Default)]
+struct Journal(Arc<Mutex<Vec<(String, bool)>>>);
+
+impl Middleware for Journal {
+    fn after(&self, method: &str, result: &Result<Value>) {
+        self.0.lock().unwrap().push((method.to_string(), result.is_ok()));
+    }
+}
+
+#[tokio::test]
+async fn modifies_methods_and_observes_results() {
+    let mock = MockVk::users();
+    let journal = Journal::default();
+
+    let client: Client<MockVk> = client::Builder::new()
+        .middleware(Lang)
+        .middleware(journal.clone())
+        .build(mock.configs(1).into_iter());
+
+    let methods = (1..=5).map(|user_id| {
+        Method::new(
+            "users.get",
+            Params::try_from(PairsArray([("user_id", user_id)])).unwrap(),
+        )
+    });
+    join_all(methods.map(|method| client.method(method))).await;
+    let _ = client.method(Method::new("wall.get", Params::new())).await;
+
+    for (_, params) in mock.sent().iter().flat_map(|sent| &sent.methods) {
+        assert_eq!(param(params, "lang").as_deref(), Some("en"));
+    }
+
+    let journal = journal.0.lock().unwrap();
+    assert_eq!(journal.len(), 6);
+    assert_eq!(journal.iter().filter(|(_, ok)| *ok).count(), 5);
+    assert!(journal.contains(&(String::from("wall.get"), false)));
+}
+
+#[tokio::test]
+async fn observes_cached_coalesced_and_merged_results_per_caller() {
+    let mock = MockVk::users();
+    let journal = Journal::default();
+
+    let client: Client<MockVk> = client::Builder::new()
+        .middleware(journal.clone())
+        .cache_method("users.get", Duration::from_secs(60))
+        .coalesce(true)
+        .merge(Strategy::users_get())
+        .build(mock.configs(1).into_iter());
+
+    // The second call joins the first one, the third is merged with it
+    let calls = [users_get(1), users_get(1), users_get(2)];
+    let results = join_all(calls.map(|method| client.method(method))).await;
+    assert!(results.iter().all(Result::is_ok));
+
+    // Answered by the cache
+    client.method(users_get(2)).await.unwrap();
+
+    assert_eq!(mock.sent().len(), 1);
+    assert_eq!(
+        *journal.0.lock().unwrap(),
+        vec![(String::from("users.get"), true); 4]
+    );
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::mock::{param, users_get, MockVk};
+use futures::future::join_all;
+use vk_executive::{Client, Config, Method};
+
+fn client(mock: &MockVk) -> Client<MockVk> {
+    let configs: Vec<Config<MockVk>> = mock
+        .configs(1)
+        .into_iter()
+        .map(|mut config| {
+            config.params.insert(String::from("lang"), String::from("ru"));
+            config
+        })
+        .collect();
+
+    Client::from_configs(configs.into_iter())
+}
+
+fn users_get_in(user_id: u64, lang: Option<&str>) -> Method {
+    let mut method = users_get(user_id);
+    if let Some(lang) = lang {
+        method.params.insert("lang", lang);
+    }
+
+    method
+}
+
+#[tokio::test]
+async fn default_params_are_sent_with_method() {
+    let mock = MockVk::users();
+    let client = client(&mock);
+
+    client.method(users_get_in(1, None)).await.unwrap();
+    client.method(users_get_in(2, Some("en"))).await.unwrap();
+
+    let langs: Vec<String> = mock
+        .sent()
+        .iter()
+        .map(|sent| param(&sent.methods[0].1, "lang").unwrap())
+        .collect();
+
+    assert_eq!(langs, ["ru", "en"]);
+}
+
+#[tokio::test]
+async fn default_params_are_sent_with_execute() {
+    let mock = MockVk::users();
+    let client = client(&mock);
+
+    let methods = (1..=10).map(|user_id| users_get_in(user_id, (user_id == 1).then_some("en")));
+    let results = join_all(methods.map(|method| client.method(method))).await;
+    assert!(results.iter().all(Result::is_ok));
+
+    let execute = mock
+        .sent()
+        .into_iter()
+        .find(|sent| sent.methods.len() > 1)
+        .unwrap();
+
+    assert_eq!(param(&execute.query, "lang").as_deref(), Some("ru"));
+
+    for (_, params) in &execute.methods {
+        let expected = if param(params, "user_id").as_deref() == Some("1") { "en" } else { "ru" };
+        assert_eq!(param(params, "lang").as_deref(), Some(expected));
+    }
+}
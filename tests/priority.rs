@@ -0,0 +1,28 @@
+mod common;
+
+use common::mock::{param, users_get, MockVk};
+use futures::future::join_all;
+use vk_executive::client::{Options, Priority};
+use vk_executive::Client;
+
+#[tokio::test]
+async fn high_priority_methods_go_first() {
+    let mock = MockVk::users();
+    let client = Client::from_configs(mock.configs(1).into_iter());
+
+    // More low priority methods than fit in one execute are queued before the high priority one
+    let low = (1..=40).map(|user_id| {
+        client.method_with(users_get(user_id), Options::new().priority(Priority::Low))
+    });
+    let high = client.method_with(users_get(1000), Options::new().priority(Priority::High));
+
+    let (low, high) = futures::join!(join_all(low), high);
+    assert!(low.into_iter().all(|result| result.is_ok()));
+    high.unwrap();
+
+    let sent = mock.sent();
+    let (_, first) = &sent[0].methods[0];
+
+    assert_eq!(param(first, "user_id").as_deref(), Some("1000"));
+    assert_eq!(sent.len(), 2);
+}
@@ -0,0 +1,140 @@
+use vk_executive::config;
+use vk_executive::{Client, Error, Method};
+use vk_method::Params;
+
+use serde_json::json;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::timeout;
+
+const RESPONSE: &str = "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 14\r\n\r\n{\"response\":1}";
+
+async fn read_head(stream: &mut TcpStream) -> String {
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        head.push(stream.read_u8().await.unwrap());
+    }
+    String::from_utf8(head).unwrap()
+}
+
+/// Serves api through the tunnel right in the proxy
+async fn serve_api(mut stream: TcpStream) {
+    let head = read_head(&mut stream).await;
+    assert!(head.starts_with("POST /method/users.get"), "{head}");
+    stream.write_all(RESPONSE.as_bytes()).await.unwrap();
+    // Keep connection open until client reads response
+    let _ = stream.read(&mut [0; 1024]).await;
+}
+
+/// Stand-in HTTP proxy accepting one `CONNECT`
+async fn http_proxy(listener: TcpListener, authorization: Option<&'static str>) {
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let head = read_head(&mut stream).await;
+
+    assert!(head.starts_with("CONNECT api.vk.test:80 HTTP/1.1\r\n"), "{head}");
+    let authorized = authorization.map_or(true, |authorization| {
+        head.contains(&format!("Proxy-Authorization: Basic {authorization}\r\n"))
+    });
+
+    if authorized {
+        stream
+            .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+            .await
+            .unwrap();
+        serve_api(stream).await;
+    } else {
+        stream
+            .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+            .await
+            .unwrap();
+    }
+}
+
+/// Stand-in SOCKS5 proxy accepting one `CONNECT` with username and password
+async fn socks5_proxy(listener: TcpListener) {
+    let (mut stream, _) = listener.accept().await.unwrap();
+
+    let mut greeting = [0; 4];
+    stream.read_exact(&mut greeting).await.unwrap();
+    assert_eq!(greeting, [5, 2, 0, 2]);
+    stream.write_all(&[5, 2]).await.unwrap();
+
+    let mut auth = [0; 13];
+    stream.read_exact(&mut auth).await.unwrap();
+    assert_eq!(&auth, b"\x01\x04user\x06secret");
+    stream.write_all(&[1, 0]).await.unwrap();
+
+    let mut request = vec![0; 5 + "api.vk.test".len() + 2];
+    stream.read_exact(&mut request).await.unwrap();
+    assert_eq!(&request[..5], [5, 1, 0, 3, 11]);
+    assert_eq!(&request[5..16], b"api.vk.test");
+    assert_eq!(&request[16..], 80u16.to_be_bytes());
+    stream
+        .write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0, 80])
+        .await
+        .unwrap();
+
+    serve_api(stream).await;
+}
+
+async fn call_through(proxy: &str) -> vk_executive::Result<serde_json::Value> {
+    let config = config::Builder::new()
+        .proxy(proxy)
+        .unwrap()
+        .api_url("http://api.vk.test/")
+        .token("token")
+        .build()
+        .unwrap();
+    let client = Client::from_configs([config].into_iter());
+
+    timeout(
+        Duration::from_secs(5),
+        client.method(Method::new("users.get", Params::new())),
+    )
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn tunnels_through_http_proxy() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    // user:secret
+    let proxy = tokio::spawn(http_proxy(listener, Some("dXNlcjpzZWNyZXQ=")));
+
+    let response = call_through(&format!("http://user:secret@{address}")).await;
+
+    assert_eq!(response.unwrap(), json!(1));
+    proxy.await.unwrap();
+}
+
+#[tokio::test]
+async fn tunnels_through_socks5_proxy() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let proxy = tokio::spawn(socks5_proxy(listener));
+
+    let response = call_through(&format!("socks5://user:secret@{address}")).await;
+
+    assert_eq!(response.unwrap(), json!(1));
+    proxy.await.unwrap();
+}
+
+#[tokio::test]
+async fn rejected_tunnel_is_network_error() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let proxy = tokio::spawn(http_proxy(listener, Some("dXNlcjpzZWNyZXQ=")));
+
+    let response = call_through(&format!("http://user:wrong@{address}")).await;
+
+    assert!(matches!(response, Err(Error::Network(_))), "{response:?}");
+    proxy.await.unwrap();
+}
+
+#[test]
+fn invalid_proxy_url() {
+    let error = config::Builder::new().proxy("socks4://127.0.0.1").unwrap_err();
+    assert!(matches!(error, config::BuildError::InvalidProxyUrl(..)));
+}
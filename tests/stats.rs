@@ -0,0 +1,65 @@
+mod common;
+
+use common::mock::{param, users_get, MockVk};
+use futures::future::join_all;
+use serde_json::json;
+use vk_executive::config::Aimd;
+use vk_executive::{Client, Config};
+use vk_method::{Method, Params};
+
+use std::time::Duration;
+
+#[tokio::test]
+async fn counts_requests_methods_and_errors() {
+    let mock = MockVk::users();
+    let client = Client::from_configs(mock.configs(1).into_iter());
+
+    let results = join_all((1..=30).map(|id| client.method(users_get(id)))).await;
+    assert!(results.iter().all(Result::is_ok));
+
+    let error = client.method(Method::new("wall.get", Params::new())).await;
+    assert!(error.is_err());
+
+    let stats = client.stats();
+    let worker = &stats.workers[0];
+
+    assert_eq!(stats.queue_depth, 0);
+    assert_eq!(worker.requests, mock.sent().len() as u64);
+    assert_eq!(worker.methods, 31);
+    assert_eq!(worker.batches.iter().sum::<u64>(), worker.requests);
+    assert_eq!(worker.vk_errors.get(&3), Some(&1));
+    assert_eq!(worker.network_errors, 0);
+    assert!(worker.fill_ratio() > 0.0);
+}
+
+#[tokio::test]
+async fn adaptive_rate_follows_rate_limit_errors() {
+    let mock = MockVk::new(|_, params| match param(params, "user_id").as_deref() {
+        Some("0") => Err((6, String::from("Too many requests per second"))),
+        _ => Ok(json!([])),
+    });
+    let configs: Vec<Config<MockVk>> = mock
+        .configs(1)
+        .into_iter()
+        .map(|mut config| {
+            config.time_between_requests = Duration::from_millis(100);
+            config.adaptive = Some(Aimd {
+                increase: 1.0,
+                ..Aimd::default()
+            });
+            config
+        })
+        .collect();
+    let client = Client::from_configs(configs.into_iter());
+
+    for id in 1..=3 {
+        client.method(users_get(id)).await.unwrap();
+    }
+    let faster = client.stats().workers[0].time_between_requests;
+    assert!(faster < Duration::from_millis(100));
+
+    client.method(users_get(0)).await.unwrap_err();
+    let slower = client.stats().workers[0].time_between_requests;
+    assert!(slower > faster);
+    assert!((client.stats().workers[0].rate().unwrap() - 1.0 / slower.as_secs_f64()).abs() < 1e-9);
+}
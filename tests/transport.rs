@@ -0,0 +1,70 @@
+use vk_executive::config::{self, Transport};
+use vk_executive::{Client, Method};
+use vk_method::Params;
+
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::timeout;
+
+/// Accepts one connection of the client sending a method to `{scheme}://` url
+async fn accept(scheme: &str, transport: Transport) -> TcpStream {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let config = config::Builder::new()
+        .transport(&transport)
+        .api_url(format!("{scheme}://{address}/"))
+        .token("token")
+        .build()
+        .unwrap();
+    let client = Client::from_configs([config].into_iter());
+
+    tokio::spawn(async move {
+        let _ = client.method(Method::new("users.get", Params::new())).await;
+    });
+
+    let (stream, _) = timeout(Duration::from_secs(5), listener.accept())
+        .await
+        .unwrap()
+        .unwrap();
+
+    stream
+}
+
+/// Accepts one connection and returns first bytes sent by the client
+async fn first_bytes(transport: Transport, len: usize) -> Vec<u8> {
+    let mut stream = accept("http", transport).await;
+
+    let mut bytes = vec![0; len];
+    stream.read_exact(&mut bytes).await.unwrap();
+    bytes
+}
+
+#[tokio::test]
+async fn http2_only_speaks_http2() {
+    let bytes = first_bytes(Transport::new().http2_only(true).nodelay(true), 24).await;
+    assert_eq!(bytes, b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n");
+}
+
+#[tokio::test]
+async fn http1_by_default() {
+    let bytes = first_bytes(Transport::new(), 5).await;
+    assert_eq!(bytes, b"POST ");
+}
+
+/// HTTP/2 over TLS is agreed on with ALPN of the client hello
+#[cfg(feature = "rustls")]
+#[tokio::test]
+async fn http2_only_offers_h2_over_tls() {
+    let mut stream = accept("https", Transport::new().http2_only(true)).await;
+
+    let mut header = [0; 5];
+    stream.read_exact(&mut header).await.unwrap();
+    assert_eq!(header[0], 0x16, "not a TLS handshake");
+
+    let mut hello = vec![0; usize::from(u16::from_be_bytes([header[3], header[4]]))];
+    stream.read_exact(&mut hello).await.unwrap();
+
+    assert!(hello.windows(3).any(|bytes| bytes == b"\x02h2"));
+}
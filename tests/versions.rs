@@ -0,0 +1,100 @@
+mod common;
+
+use common::mock::{param, users_get, MockVk};
+use futures::future::join_all;
+use vk_executive::{Client, Config, Method};
+
+fn users_get_of(user_id: u64, version: Option<&str>) -> Method {
+    let mut method = users_get(user_id);
+    if let Some(version) = version {
+        method.params.insert("v", version);
+    }
+
+    method
+}
+
+#[tokio::test]
+async fn methods_of_different_versions_are_sent_separately() {
+    let mock = MockVk::users();
+    let client = Client::from_configs(mock.configs(1).into_iter());
+
+    let methods = (1..=30u64).map(|user_id| {
+        let version = (user_id % 3 == 0).then_some("5.199");
+        users_get_of(user_id, version)
+    });
+    let results = join_all(methods.map(|method| client.method(method))).await;
+    assert!(results.iter().all(Result::is_ok));
+
+    let sent = mock.sent();
+    let mut count = 0;
+
+    for sent in &sent {
+        let version = param(&sent.query, "v").unwrap();
+
+        for (_, params) in &sent.methods {
+            let user_id: u64 = param(params, "user_id").unwrap().parse().unwrap();
+            let expected = if user_id % 3 == 0 { "5.199" } else { "5.103" };
+
+            assert_eq!(version, expected);
+            assert!(param(params, "v").is_none());
+            count += 1;
+        }
+    }
+
+    assert_eq!(count, 30);
+}
+
+#[tokio::test]
+async fn config_sets_version_of_method() {
+    let mock = MockVk::users();
+    let configs: Vec<Config<MockVk>> = mock
+        .configs(1)
+        .into_iter()
+        .map(|mut config| {
+            config
+                .method_versions
+                .insert(String::from("users.get"), String::from("5.131"));
+            config
+        })
+        .collect();
+    let client = Client::from_configs(configs.into_iter());
+
+    client.method(users_get_of(1, None)).await.unwrap();
+    client.method(users_get_of(2, Some("5.199"))).await.unwrap();
+
+    let versions: Vec<String> = mock
+        .sent()
+        .iter()
+        .map(|sent| param(&sent.query, "v").unwrap())
+        .collect();
+
+    assert_eq!(versions, ["5.131", "5.199"]);
+}
+
+#[tokio::test]
+async fn postponed_methods_are_left_to_other_workers() {
+    let mock = MockVk::users();
+    let client = Client::from_configs(mock.configs(2).into_iter());
+
+    // The first taken method is of other version than the rest,
+    // so every other method taken with it is postponed
+    let methods = (0..=100u64).map(|user_id| {
+        let version = (user_id == 0).then_some("5.199");
+        users_get_of(user_id, version)
+    });
+    let results = join_all(methods.map(|method| client.method(method))).await;
+    assert!(results.iter().all(Result::is_ok));
+
+    let sent = mock.sent();
+    for token in ["token0", "token1"] {
+        let methods: usize = sent
+            .iter()
+            .filter(|sent| sent.token == token)
+            .map(|sent| sent.methods.len())
+            .sum();
+
+        // Neither worker takes more than its share of the queue
+        assert!(methods > 25, "{token} sent {methods} methods");
+    }
+    assert_eq!(client.stats().queue_depth, 0);
+}